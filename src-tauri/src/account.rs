@@ -12,33 +12,115 @@ use isideload::{
 use keyring::Entry;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::{path::PathBuf, sync::OnceLock, time::Duration};
-use tauri::{AppHandle, Emitter, Listener, Manager, State, Window};
+use std::{
+    path::PathBuf,
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+use tauri::{AppHandle, Emitter, Listener, State, Window};
 use tauri_plugin_store::StoreExt;
 use tracing::{debug, warn};
 
-use crate::sideload::{SideloaderGuard, SideloaderMutex};
+use crate::{
+    iloader_core::{IloaderCore, PromptAbort, Prompter},
+    quota::AppIdShortfall,
+    sideload::LoggedInAccount,
+};
+
+/// Asks the frontend for 2FA codes, certificate and App ID choices through window events.
+///
+/// `prompt-cancelled` is emitted when a prompt times out or is aborted, so the dialog can close.
+struct WindowPrompter {
+    window: Window,
+}
+
+impl Prompter for WindowPrompter {
+    fn two_factor_code(&self) -> Option<String> {
+        self.window
+            .emit("2fa-required", ())
+            .expect("Failed to emit 2fa-required event");
+
+        let (tx, rx) = std::sync::mpsc::channel::<String>();
+        let handler_id = self.window.listen("2fa-recieved", move |event| {
+            let code = event.payload();
+            let _ = tx.send(code.to_string());
+        });
+
+        let result = PromptAbort::current().wait(&rx, Duration::from_secs(120));
+        self.window.unlisten(handler_id);
+
+        match result {
+            Some(code) => {
+                let code = code.trim_matches('"').to_string();
+                Some(code)
+            }
+            None => {
+                let _ = self.window.emit("prompt-cancelled", ());
+                None
+            }
+        }
+    }
+
+    fn certificates_to_revoke(&self, certs: Vec<CertificateInfo>) -> Option<Vec<String>> {
+        self.window
+            .emit("max-certs-reached", certs)
+            .expect("Failed to emit max-certs-reached event");
+
+        let (tx, rx) = std::sync::mpsc::channel::<Option<Vec<String>>>();
+        let handler_id = self.window.listen("max-certs-response", move |event| {
+            let certs = event.payload();
+            let certs = serde_json::from_str::<Option<Vec<String>>>(certs).unwrap_or(None);
+            let _ = tx.send(certs);
+        });
+
+        let result = PromptAbort::current().wait(&rx, Duration::from_secs(300));
+        self.window.unlisten(handler_id);
+        if result.is_none() {
+            let _ = self.window.emit("prompt-cancelled", ());
+        }
+        result.flatten()
+    }
+
+    fn app_ids_to_delete(&self, shortfall: AppIdShortfall) -> Option<Vec<String>> {
+        self.window
+            .emit("app-ids-required", shortfall)
+            .expect("Failed to emit app-ids-required event");
+
+        let (tx, rx) = std::sync::mpsc::channel::<Option<Vec<String>>>();
+        let handler_id = self.window.listen("app-ids-response", move |event| {
+            let ids = serde_json::from_str::<Option<Vec<String>>>(event.payload()).unwrap_or(None);
+            let _ = tx.send(ids);
+        });
+
+        let result = PromptAbort::current().wait(&rx, Duration::from_secs(300));
+        self.window.unlisten(handler_id);
+        if result.is_none() {
+            let _ = self.window.emit("prompt-cancelled", ());
+        }
+        result.flatten()
+    }
+}
 
 #[tauri::command]
 pub async fn login_new(
     handle: AppHandle,
     window: Window,
-    sideloader_state: State<'_, SideloaderMutex>,
+    core: State<'_, IloaderCore>,
     email: String,
     password: String,
     anisette_server: String,
     save_credentials: bool,
 ) -> Result<(), String> {
-    let account = login(&handle, &window, &email, &password, anisette_server).await?;
-    let mut sideloader_guard = sideloader_state.lock().unwrap();
-    *sideloader_guard = Some(account);
+    core.login(
+        &email,
+        &password,
+        anisette_server,
+        Arc::new(WindowPrompter { window }),
+    )
+    .await?;
 
     if save_credentials {
-        let pass_entry = Entry::new("iloader", &email)
-            .map_err(|e| format!("Failed to create keyring entry for credentials: {:?}.", e))?;
-        pass_entry
-            .set_password(&password)
-            .map_err(|e| format!("Failed to save credentials to keyring: {:?}", e))?;
+        save_password(&email, &password)?;
         let store = handle
             .store("data.json")
             .map_err(|e| format!("Failed to get store: {:?}", e))?;
@@ -59,31 +141,24 @@ pub async fn login_new(
 
 #[tauri::command]
 pub async fn login_stored(
-    handle: AppHandle,
     window: Window,
     email: String,
     anisette_server: String,
-    sideloader_state: State<'_, SideloaderMutex>,
+    core: State<'_, IloaderCore>,
 ) -> Result<(), String> {
-    let pass_entry = Entry::new("iloader", &email)
-        .map_err(|e| format!("Failed to create keyring entry for credentials: {:?}.", e))?;
-    let password = pass_entry
-        .get_password()
-        .map_err(|e| format!("Failed to get credentials: {:?}", e))?;
-    let account = login(&handle, &window, &email, &password, anisette_server).await?;
-    let mut sideloader_guard = sideloader_state.lock().unwrap();
-    *sideloader_guard = Some(account);
-
-    Ok(())
+    let password = stored_password(&email)?;
+    core.login(
+        &email,
+        &password,
+        anisette_server,
+        Arc::new(WindowPrompter { window }),
+    )
+    .await
 }
 
 #[tauri::command]
 pub fn delete_account(handle: AppHandle, email: String) -> Result<(), String> {
-    let pass_entry = Entry::new("iloader", &email)
-        .map_err(|e| format!("Failed to create keyring entry for credentials: {:?}.", e))?;
-    pass_entry
-        .delete_credential()
-        .map_err(|e| format!("Failed to delete credentials: {:?}", e))?;
+    delete_password(&email)?;
     let store = handle
         .store("data.json")
         .map_err(|e| format!("Failed to get store: {:?}", e))?;
@@ -99,18 +174,13 @@ pub fn delete_account(handle: AppHandle, email: String) -> Result<(), String> {
 }
 
 #[tauri::command]
-pub fn logged_in_as(sideloader_state: State<'_, SideloaderMutex>) -> Option<String> {
-    let sideloader_guard = sideloader_state.lock().unwrap();
-    if let Some(account) = &*sideloader_guard {
-        return Some(account.get_email().to_string());
-    }
-    None
+pub fn logged_in_as(core: State<'_, IloaderCore>) -> Option<String> {
+    core.logged_in_as()
 }
 
 #[tauri::command]
-pub fn invalidate_account(sideloader_state: State<'_, SideloaderMutex>) {
-    let mut sideloader_guard = sideloader_state.lock().unwrap();
-    *sideloader_guard = None;
+pub fn invalidate_account(core: State<'_, IloaderCore>) {
+    core.logout();
 }
 
 #[tauri::command]
@@ -124,37 +194,46 @@ pub fn reset_anisette_state() -> Result<(), String> {
     Ok(())
 }
 
-async fn login(
-    app: &AppHandle,
-    window: &Window,
+pub fn save_password(email: &str, password: &str) -> Result<(), String> {
+    let pass_entry = Entry::new("iloader", email)
+        .map_err(|e| format!("Failed to create keyring entry for credentials: {:?}.", e))?;
+    pass_entry
+        .set_password(password)
+        .map_err(|e| format!("Failed to save credentials to keyring: {:?}", e))
+}
+
+pub fn stored_password(email: &str) -> Result<String, String> {
+    let pass_entry = Entry::new("iloader", email)
+        .map_err(|e| format!("Failed to create keyring entry for credentials: {:?}.", e))?;
+    pass_entry
+        .get_password()
+        .map_err(|e| format!("Failed to get credentials: {:?}", e))
+}
+
+pub fn delete_password(email: &str) -> Result<(), String> {
+    let pass_entry = Entry::new("iloader", email)
+        .map_err(|e| format!("Failed to create keyring entry for credentials: {:?}.", e))?;
+    pass_entry
+        .delete_credential()
+        .map_err(|e| format!("Failed to delete credentials: {:?}", e))
+}
+
+/// Log in to an Apple account and build a `Sideloader` for it.
+///
+/// `tfa_closure` is called when a 2FA code is required, and `max_certs_callback` is called with
+/// the existing certificates when no more can be created, returning the serial numbers to revoke.
+/// `data_dir` is only used for storage when the keyring is unavailable.
+pub async fn login(
     email: &str,
     password: &str,
     anisette_server: String,
+    data_dir: PathBuf,
+    tfa_closure: impl Fn() -> Option<String>,
+    max_certs_callback: impl Fn(&Vec<DevelopmentCertificate>) -> Option<Vec<String>>
+    + Send
+    + Sync
+    + 'static,
 ) -> Result<Sideloader, String> {
-    let window_clone = window.clone();
-    let tfa_closure = move || -> Option<String> {
-        window_clone
-            .emit("2fa-required", ())
-            .expect("Failed to emit 2fa-required event");
-
-        let (tx, rx) = std::sync::mpsc::channel::<String>();
-        let handler_id = window_clone.listen("2fa-recieved", move |event| {
-            let code = event.payload();
-            let _ = tx.send(code.to_string());
-        });
-
-        let result = rx.recv_timeout(Duration::from_secs(120));
-        window_clone.unlisten(handler_id);
-
-        match result {
-            Ok(code) => {
-                let code = code.trim_matches('"').to_string();
-                Some(code)
-            }
-            Err(_) => None,
-        }
-    };
-
     let anisette_url = if !anisette_server.starts_with("http") {
         format!("https://{}", anisette_server)
     } else {
@@ -171,10 +250,6 @@ async fn login(
         )
     } else {
         warn!("Keyring storage is not available, falling back to file storage (less secure)");
-        let data_dir = app
-            .path()
-            .app_data_dir()
-            .unwrap_or_else(|_| PathBuf::from("Failed to get app data directory"));
         (
             Box::new(FsStorage::new(data_dir.clone())),
             Box::new(FsStorage::new(data_dir)),
@@ -200,36 +275,6 @@ async fn login(
 
     debug!("Created developer session");
 
-    let max_certs_callback = {
-        let window_clone = window.clone();
-        move |certs: &Vec<DevelopmentCertificate>| -> Option<Vec<String>> {
-            let cert_infos: Vec<CertificateInfo> = certs
-                .iter()
-                .map(|cert| CertificateInfo {
-                    name: cert.name.clone(),
-                    certificate_id: cert.certificate_id.clone(),
-                    serial_number: cert.serial_number.clone(),
-                    machine_name: cert.machine_name.clone(),
-                    machine_id: cert.machine_id.clone(),
-                })
-                .collect();
-            window_clone
-                .emit("max-certs-reached", cert_infos)
-                .expect("Failed to emit max-certs-reached event");
-
-            let (tx, rx) = std::sync::mpsc::channel::<Option<Vec<String>>>();
-            let handler_id = window_clone.listen("max-certs-response", move |event| {
-                let certs = event.payload();
-                let certs = serde_json::from_str::<Option<Vec<String>>>(certs).unwrap_or(None);
-                let _ = tx.send(certs);
-            });
-
-            let result = rx.recv_timeout(Duration::from_secs(300));
-            window_clone.unlisten(handler_id);
-            result.unwrap_or(None)
-        }
-    };
-
     // TODO: Team Selection
 
     let sideloader = SideloaderBuilder::new(dev_session, email.to_lowercase())
@@ -253,99 +298,156 @@ pub struct CertificateInfo {
     pub machine_id: Option<String>,
 }
 
-#[tauri::command]
-pub async fn get_certificates(
-    sideloader_state: State<'_, SideloaderMutex>,
-) -> Result<Vec<CertificateInfo>, String> {
-    let mut sideloader = SideloaderGuard::take(&sideloader_state)?;
-
-    let team = sideloader
-        .get_mut()
-        .get_team()
-        .await
-        .map_err(|e| e.to_string())?;
-    let dev_session = sideloader.get_mut().get_dev_session();
-
-    let certificates = dev_session
-        .list_all_development_certs(&team, None)
-        .await
-        .map_err(|e| format!("Failed to get development certificates: {:?}.", e))?;
-
-    Ok(certificates
-        .into_iter()
-        .map(|cert| CertificateInfo {
+impl From<DevelopmentCertificate> for CertificateInfo {
+    fn from(cert: DevelopmentCertificate) -> Self {
+        CertificateInfo {
             name: cert.name,
             certificate_id: cert.certificate_id,
             serial_number: cert.serial_number,
             machine_name: cert.machine_name,
             machine_id: cert.machine_id,
-        })
-        .collect())
+        }
+    }
 }
 
-#[tauri::command]
-pub async fn revoke_certificate(
-    serial_number: String,
-    sideloader_state: State<'_, SideloaderMutex>,
-) -> Result<(), String> {
-    let mut sideloader = SideloaderGuard::take(&sideloader_state)?;
+impl IloaderCore {
+    /// Log in and replace the current account. Prompts go through `prompter`.
+    pub async fn login(
+        &self,
+        email: &str,
+        password: &str,
+        anisette_server: String,
+        prompter: Arc<dyn Prompter>,
+    ) -> Result<(), String> {
+        let tfa_prompter = prompter.clone();
+        let certs_prompter = prompter.clone();
+        let sideloader = login(
+            email,
+            password,
+            anisette_server,
+            self.data_dir.clone(),
+            move || tfa_prompter.two_factor_code(),
+            move |certs: &Vec<DevelopmentCertificate>| {
+                certs_prompter.certificates_to_revoke(
+                    certs.iter().cloned().map(CertificateInfo::from).collect(),
+                )
+            },
+        )
+        .await?;
+        *self.sideloader.lock().unwrap() = Some(LoggedInAccount::new(sideloader, prompter));
+        Ok(())
+    }
 
-    let team = sideloader
-        .get_mut()
-        .get_team()
-        .await
-        .map_err(|e| e.to_string())?;
-    let dev_session = sideloader.get_mut().get_dev_session();
+    pub fn logged_in_as(&self) -> Option<String> {
+        let sideloader_guard = self.sideloader.lock().unwrap();
+        sideloader_guard
+            .as_ref()
+            .map(|account| account.email.clone())
+    }
 
-    dev_session
-        .revoke_development_cert(&team, &serial_number, None)
-        .await
-        .map_err(|e| format!("Failed to revoke development certificates: {:?}.", e))?;
+    pub fn logout(&self) {
+        *self.sideloader.lock().unwrap() = None;
+    }
 
-    Ok(())
-}
+    pub async fn certificates(&self) -> Result<Vec<CertificateInfo>, String> {
+        let mut sideloader = self.lock_sideloader().await?;
+
+        let team = sideloader
+            .get_mut()
+            .get_team()
+            .await
+            .map_err(|e| e.to_string())?;
+        let dev_session = sideloader.get_mut().get_dev_session();
+
+        let certificates = dev_session
+            .list_all_development_certs(&team, None)
+            .await
+            .map_err(|e| format!("Failed to get development certificates: {:?}.", e))?;
+
+        Ok(certificates
+            .into_iter()
+            .map(CertificateInfo::from)
+            .collect())
+    }
 
-#[tauri::command]
-pub async fn list_app_ids(
-    sideloader_state: State<'_, SideloaderMutex>,
-) -> Result<ListAppIdsResponse, String> {
-    let mut sideloader = SideloaderGuard::take(&sideloader_state)?;
-
-    let team = sideloader
-        .get_mut()
-        .get_team()
-        .await
-        .map_err(|e| e.to_string())?;
-    let dev_session = sideloader.get_mut().get_dev_session();
+    pub async fn revoke_certificate(&self, serial_number: &str) -> Result<(), String> {
+        let mut sideloader = self.lock_sideloader().await?;
 
-    let response = dev_session
-        .list_app_ids(&team, None)
-        .await
-        .map_err(|e| e.to_string())?;
+        let team = sideloader
+            .get_mut()
+            .get_team()
+            .await
+            .map_err(|e| e.to_string())?;
+        let dev_session = sideloader.get_mut().get_dev_session();
+
+        dev_session
+            .revoke_development_cert(&team, serial_number, None)
+            .await
+            .map_err(|e| format!("Failed to revoke development certificates: {:?}.", e))?;
+
+        Ok(())
+    }
+
+    pub async fn app_ids(&self) -> Result<ListAppIdsResponse, String> {
+        let mut sideloader = self.lock_sideloader().await?;
 
-    Ok(response.clone())
+        let team = sideloader
+            .get_mut()
+            .get_team()
+            .await
+            .map_err(|e| e.to_string())?;
+        let dev_session = sideloader.get_mut().get_dev_session();
+
+        let response = dev_session
+            .list_app_ids(&team, None)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(response.clone())
+    }
+
+    pub async fn delete_app_id(&self, app_id_id: &str) -> Result<(), String> {
+        let mut sideloader = self.lock_sideloader().await?;
+
+        let team = sideloader
+            .get_mut()
+            .get_team()
+            .await
+            .map_err(|e| e.to_string())?;
+        let dev_session = sideloader.get_mut().get_dev_session();
+
+        dev_session
+            .delete_app_id(&team, app_id_id, None)
+            .await
+            .map_err(|e| format!("Failed to delete App ID: {:?}.", e))?;
+
+        Ok(())
+    }
 }
 
 #[tauri::command]
-pub async fn delete_app_id(
-    app_id_id: String,
-    sideloader_state: State<'_, SideloaderMutex>,
-) -> Result<(), String> {
-    let mut sideloader = SideloaderGuard::take(&sideloader_state)?;
+pub async fn get_certificates(
+    core: State<'_, IloaderCore>,
+) -> Result<Vec<CertificateInfo>, String> {
+    core.certificates().await
+}
 
-    let team = sideloader
-        .get_mut()
-        .get_team()
-        .await
-        .map_err(|e| e.to_string())?;
-    let dev_session = sideloader.get_mut().get_dev_session();
+#[tauri::command]
+pub async fn revoke_certificate(
+    serial_number: String,
+    core: State<'_, IloaderCore>,
+) -> Result<(), String> {
+    core.revoke_certificate(&serial_number).await
+}
 
-    dev_session
-        .delete_app_id(&team, &app_id_id, None)
-        .await
-        .map_err(|e| format!("Failed to delete App ID: {:?}.", e))?;
+#[tauri::command]
+pub async fn list_app_ids(core: State<'_, IloaderCore>) -> Result<ListAppIdsResponse, String> {
+    core.app_ids().await
+}
 
-    Ok(())
+#[tauri::command]
+pub async fn delete_app_id(app_id_id: String, core: State<'_, IloaderCore>) -> Result<(), String> {
+    core.delete_app_id(&app_id_id).await
 }
 
 static KEYRING_AVAILABLE: OnceLock<bool> = OnceLock::new();
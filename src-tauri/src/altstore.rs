@@ -0,0 +1,211 @@
+use serde::{Deserialize, Serialize};
+use tauri::{State, Window};
+
+use crate::{iloader_core::IloaderCore, ipa::AppOverrides, jobs::JobKind, operation::Operation};
+
+/// An AltStore-format source, as used by AltStore and SideStore to distribute apps.
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AltStoreFeed {
+    pub name: String,
+    #[serde(default)]
+    pub apps: Vec<AltStoreApp>,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AltStoreApp {
+    pub name: String,
+    pub bundle_identifier: String,
+    #[serde(default)]
+    pub developer_name: Option<String>,
+    #[serde(default, rename = "iconURL")]
+    pub icon_url: Option<String>,
+    /// Newest first
+    #[serde(default)]
+    pub versions: Vec<AltStoreVersion>,
+
+    // Sources from before AltStore 2.0 describe a single version on the app itself
+    #[serde(default, skip_serializing)]
+    version: Option<String>,
+    #[serde(default, rename = "downloadURL", skip_serializing)]
+    download_url: Option<String>,
+    #[serde(default, skip_serializing)]
+    size: Option<u64>,
+    #[serde(default, skip_serializing)]
+    version_date: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AltStoreVersion {
+    pub version: String,
+    #[serde(default)]
+    pub build_version: Option<String>,
+    #[serde(default)]
+    pub date: Option<String>,
+    #[serde(rename = "downloadURL")]
+    pub download_url: String,
+    /// In bytes
+    #[serde(default)]
+    pub size: Option<u64>,
+    #[serde(default, rename = "minOSVersion")]
+    pub min_os_version: Option<String>,
+    #[serde(default, rename = "maxOSVersion")]
+    pub max_os_version: Option<String>,
+    /// Not part of the AltStore format, but some sources publish it
+    #[serde(default)]
+    pub sha256: Option<String>,
+}
+
+impl AltStoreFeed {
+    /// The version of the app with `bundle_id` matching `version` (either the version or the
+    /// build version), or its newest version.
+    pub fn find_version(
+        &self,
+        bundle_id: &str,
+        version: Option<&str>,
+    ) -> Result<&AltStoreVersion, String> {
+        let app = self
+            .apps
+            .iter()
+            .find(|a| a.bundle_identifier == bundle_id)
+            .ok_or_else(|| format!("{} is not in {}", bundle_id, self.name))?;
+        match version {
+            Some(version) => app
+                .versions
+                .iter()
+                .find(|v| v.version == version || v.build_version.as_deref() == Some(version))
+                .ok_or_else(|| format!("{} has no version {}", app.name, version)),
+            None => app
+                .versions
+                .first()
+                .ok_or_else(|| format!("{} has no versions", app.name)),
+        }
+    }
+}
+
+impl AltStoreApp {
+    fn move_legacy_version(&mut self) {
+        if !self.versions.is_empty() {
+            return;
+        }
+        if let (Some(version), Some(download_url)) = (self.version.take(), self.download_url.take())
+        {
+            self.versions.push(AltStoreVersion {
+                version,
+                build_version: None,
+                date: self.version_date.take(),
+                download_url,
+                size: self.size.take(),
+                min_os_version: None,
+                max_os_version: None,
+                sha256: None,
+            });
+        }
+    }
+}
+
+impl IloaderCore {
+    pub async fn altstore_feed(&self, url: &str) -> Result<AltStoreFeed, String> {
+        let mut feed: AltStoreFeed = self
+            .http_client()?
+            .get(url)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| format!("Failed to fetch source: {}", e))?
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse source: {}", e))?;
+        for app in &mut feed.apps {
+            app.move_legacy_version();
+        }
+        Ok(feed)
+    }
+
+    /// Download `version` (or the newest version) of the app with `bundle_id` from the source at
+    /// `feed_url`, then sign and install it on the device with `udid`, or the selected device.
+    pub async fn install_feed_app(
+        &self,
+        op: &Operation<'_>,
+        udid: Option<&str>,
+        feed_url: &str,
+        bundle_id: &str,
+        version: Option<&str>,
+    ) -> Result<(), String> {
+        op.start("download")?;
+        let device = op.run("download", self.device(udid)).await?;
+        let feed = op.run("download", self.altstore_feed(feed_url)).await?;
+        let version = op
+            .fail_if_err("download", feed.find_version(bundle_id, version))?
+            .clone();
+
+        op.record_device(&device.uuid);
+        op.record_app(&version.download_url);
+
+        let mut progress = op.progress_tracker("download");
+        let download = op
+            .run(
+                "download",
+                self.download_ipa(
+                    &version.download_url,
+                    version.sha256.as_deref(),
+                    |done, total| progress.update(done, total),
+                ),
+            )
+            .await?;
+        op.move_on("download", "install")?;
+        // Refreshes sign the same version again rather than updating the app
+        let origin = JobKind::InstallFeedApp {
+            feed_url: feed_url.to_string(),
+            bundle_id: bundle_id.to_string(),
+            version: Some(version.version.clone()),
+        };
+        let mut progress = op.progress_tracker("install");
+        let signing = op
+            .run(
+                "install",
+                self.install_app(
+                    &device,
+                    &download.path,
+                    &AppOverrides::default(),
+                    &origin,
+                    |done, total| progress.update(done, total),
+                ),
+            )
+            .await?;
+        op.record_signing(&signing);
+        op.complete("install")?;
+        Ok(())
+    }
+}
+
+#[tauri::command]
+pub async fn fetch_altstore_feed(
+    core: State<'_, IloaderCore>,
+    url: String,
+) -> Result<AltStoreFeed, String> {
+    core.altstore_feed(&url).await
+}
+
+#[tauri::command]
+pub async fn install_feed_app_operation(
+    window: Window,
+    core: State<'_, IloaderCore>,
+    operation_id: String,
+    feed_url: String,
+    bundle_id: String,
+    version: Option<String>,
+    udid: Option<String>,
+) -> Result<(), String> {
+    let op = core.operation_with_id(operation_id, "install_feed_app", &window)?;
+    core.install_feed_app(
+        &op,
+        udid.as_deref(),
+        &feed_url,
+        &bundle_id,
+        version.as_deref(),
+    )
+    .await
+}
@@ -1,13 +1,21 @@
-use std::sync::Mutex;
+use std::{collections::HashMap, future::Future, pin::Pin, sync::Mutex, time::Duration};
 
+use futures::StreamExt;
 use idevice::{
+    Idevice, IdeviceError, IdeviceService,
     lockdown::LockdownClient,
-    provider::UsbmuxdProvider,
-    usbmuxd::{Connection, UsbmuxdAddr, UsbmuxdConnection},
-    IdeviceService,
+    pairing_file::PairingFile,
+    provider::{IdeviceProvider, TcpProvider, UsbmuxdProvider},
+    usbmuxd::{Connection, UsbmuxdAddr, UsbmuxdConnection, UsbmuxdDevice, UsbmuxdListenEvent},
 };
 use serde::{Deserialize, Serialize};
 use tauri::State;
+use tracing::{debug, warn};
+
+use crate::{
+    iloader_core::IloaderCore,
+    network::{NetworkTarget, tcp_provider},
+};
 
 #[derive(Deserialize, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -16,85 +24,376 @@ pub struct DeviceInfo {
     pub id: u32,
     pub uuid: String,
     pub connection_type: String,
+    #[serde(default)]
+    pub status: DeviceStatus,
+    /// Set for devices added by address, which are reached without usbmuxd.
+    #[serde(default)]
+    pub network: Option<NetworkTarget>,
+}
+
+/// Whether iloader can talk to a device, see [`trust_device`] for `NotTrusted`.
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(tag = "type", content = "message")]
+pub enum DeviceStatus {
+    #[default]
+    Ready,
+    Locked,
+    NotTrusted,
+    LockdownError(String),
 }
 
 pub type DeviceInfoMutex = Mutex<Option<DeviceInfo>>;
 
 #[tauri::command]
-pub async fn list_devices() -> Result<Vec<DeviceInfo>, String> {
-    let usbmuxd = UsbmuxdConnection::default().await;
-    if usbmuxd.is_err() {
-        eprintln!("Failed to connect to usbmuxd: {:?}", usbmuxd.err());
-        return Err("Failed to connect to usbmuxd".to_string());
-    }
-    let mut usbmuxd = usbmuxd.unwrap();
+pub async fn list_devices(core: State<'_, IloaderCore>) -> Result<Vec<DeviceInfo>, String> {
+    core.devices().await
+}
 
-    let devs = usbmuxd.get_devices().await.unwrap();
+pub async fn usbmuxd_devices() -> Result<Vec<DeviceInfo>, String> {
+    let mut usbmuxd = UsbmuxdConnection::default()
+        .await
+        .map_err(|e| format!("Failed to connect to usbmuxd: {}", e))?;
+
+    let devs = usbmuxd
+        .get_devices()
+        .await
+        .map_err(|e| format!("Failed to list devices: {}", e))?;
     if devs.is_empty() {
         return Ok(vec![]);
     }
 
-    let device_info_futures: Vec<_> = devs
-        .iter()
-        .map(|d| async move {
-            let provider = d.to_provider(UsbmuxdAddr::from_env_var().unwrap(), "iloader");
-            let device_uid = d.device_id;
-            let connection_type = match d.connection_type {
-                Connection::Usb => "USB",
-                Connection::Network(_) => "Network",
-                Connection::Unknown(_) => "Unknown",
+    let addr = usbmuxd_addr()?;
+    let device_info_futures: Vec<_> = devs.iter().map(|d| device_info(d, addr.clone())).collect();
+
+    Ok(futures::future::join_all(device_info_futures).await)
+}
+
+fn usbmuxd_addr() -> Result<UsbmuxdAddr, String> {
+    UsbmuxdAddr::from_env_var().map_err(|e| format!("Invalid USBMUXD_SOCKET_ADDRESS: {}", e))
+}
+
+async fn device_info(d: &UsbmuxdDevice, addr: UsbmuxdAddr) -> DeviceInfo {
+    let provider = d.to_provider(addr, "iloader");
+    let connection_type = match d.connection_type {
+        Connection::Usb => "USB",
+        Connection::Network(_) => "Network",
+        Connection::Unknown(_) => "Unknown",
+    }
+    .to_string();
+    let mut info = DeviceInfo {
+        name: String::from("Unknown Device"),
+        id: d.device_id,
+        uuid: d.udid.clone(),
+        connection_type,
+        status: DeviceStatus::Ready,
+        network: None,
+    };
+    device_status(&provider, &mut info).await;
+    info
+}
+
+/// Reads the device name through `provider` and checks whether its pairing file is accepted.
+pub(crate) async fn device_status(provider: &dyn IdeviceProvider, info: &mut DeviceInfo) {
+    let mut lockdown_client = match LockdownClient::connect(provider).await {
+        Ok(l) => l,
+        Err(e) => {
+            warn!("Unable to connect to lockdown: {e:?}");
+            info.status = DeviceStatus::LockdownError(e.to_string());
+            return;
+        }
+    };
+
+    match lockdown_client.get_value(Some("DeviceName"), None).await {
+        Ok(name) => {
+            if let Some(name) = name.as_string() {
+                info.name = name.to_string();
             }
-            .to_string();
-
-            let mut lockdown_client = match LockdownClient::connect(&provider).await {
-                Ok(l) => l,
-                Err(e) => {
-                    eprintln!("Unable to connect to lockdown: {e:?}");
-                    return DeviceInfo {
-                        connection_type,
-                        name: String::from("Unknown Device"),
-                        id: device_uid,
-                        uuid: d.udid.clone(),
-                    };
-                }
-            };
+        }
+        Err(e) => {
+            warn!("Unable to get device name: {e:?}");
+            info.status = DeviceStatus::LockdownError(e.to_string());
+            return;
+        }
+    }
+
+    info.status = match provider.get_pairing_file().await {
+        Ok(pairing_file) => match lockdown_client.start_session(&pairing_file).await {
+            Ok(()) => DeviceStatus::Ready,
+            Err(IdeviceError::PasswordProtected) => DeviceStatus::Locked,
+            Err(IdeviceError::InvalidHostID) => DeviceStatus::NotTrusted,
+            Err(e) => DeviceStatus::LockdownError(e.to_string()),
+        },
+        Err(_) => DeviceStatus::NotTrusted,
+    };
+}
+
+/// Asks the device to trust this computer, waiting for the user to accept the dialog, and saves
+/// the resulting pair record to usbmuxd.
+pub async fn trust_device(device: &DeviceInfo) -> Result<(), String> {
+    if device.network.is_some() {
+        return Err("Network devices can only be trusted over USB".to_string());
+    }
+    let mut usbmuxd = UsbmuxdConnection::default()
+        .await
+        .map_err(|e| format!("Failed to connect to usbmuxd: {}", e))?;
+    let buid = usbmuxd
+        .get_buid()
+        .await
+        .map_err(|e| format!("Failed to get usbmuxd BUID: {}", e))?;
+    let provider = get_provider_from_connection(device, &mut usbmuxd).await?;
+
+    let mut lockdown_client = LockdownClient::connect(&provider)
+        .await
+        .map_err(|e| format!("Failed to connect to lockdown: {}", e))?;
+
+    let host_id = uuid::Uuid::new_v4().to_string().to_uppercase();
+    let mut pairing_file = match tokio::time::timeout(
+        Duration::from_secs(120),
+        lockdown_client.pair(host_id, buid, Some("iloader")),
+    )
+    .await
+    {
+        Ok(Ok(p)) => p,
+        Ok(Err(IdeviceError::PasswordProtected)) => {
+            return Err("Unlock the device and try again".to_string());
+        }
+        Ok(Err(IdeviceError::UserDeniedPairing)) => {
+            return Err("Trust was denied on the device".to_string());
+        }
+        Ok(Err(e)) => return Err(format!("Failed to pair with device: {}", e)),
+        Err(_) => return Err("Timed out waiting for the device to trust this computer".to_string()),
+    };
+    pairing_file.udid = Some(device.uuid.clone());
+
+    let pair_record = pairing_file
+        .serialize()
+        .map_err(|e| format!("Failed to serialize pair record: {}", e))?;
+    usbmuxd
+        .save_pair_record(&device.uuid, pair_record)
+        .await
+        .map_err(|e| format!("Failed to save pair record: {}", e))?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn trust_device_cmd(device: DeviceInfo) -> Result<(), String> {
+    trust_device(&device).await
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceDetails {
+    pub product_version: Option<String>,
+    pub product_type: Option<String>,
+    pub build_version: Option<String>,
+    pub hardware_model: Option<String>,
+    pub device_class: Option<String>,
+    /// `None` when it could not be read, e.g. because the device is not paired.
+    pub developer_mode_enabled: Option<bool>,
+    pub paired: bool,
+    /// Problems that will make an install fail or the installed app unusable.
+    pub warnings: Vec<String>,
+}
+
+pub async fn device_details(device: &DeviceInfo) -> Result<DeviceDetails, String> {
+    let provider = get_provider(device).await?;
+    let pairing_file = provider.get_pairing_file().await.ok();
+
+    let mut lockdown_client = LockdownClient::connect(&provider)
+        .await
+        .map_err(|e| format!("Failed to connect to lockdown: {}", e))?;
+
+    let mut details = DeviceDetails {
+        product_version: lockdown_string(&mut lockdown_client, "ProductVersion").await,
+        product_type: lockdown_string(&mut lockdown_client, "ProductType").await,
+        build_version: lockdown_string(&mut lockdown_client, "BuildVersion").await,
+        hardware_model: lockdown_string(&mut lockdown_client, "HardwareModel").await,
+        device_class: lockdown_string(&mut lockdown_client, "DeviceClass").await,
+        developer_mode_enabled: None,
+        paired: false,
+        warnings: vec![],
+    };
 
-            let device_name = lockdown_client
-                .get_value(Some("DeviceName"), None)
+    if let Some(pairing_file) = pairing_file {
+        details.paired = lockdown_client.start_session(&pairing_file).await.is_ok();
+        if details.paired {
+            details.developer_mode_enabled = lockdown_client
+                .get_value(
+                    Some("DeveloperModeStatus"),
+                    Some("com.apple.security.mac.amfi"),
+                )
                 .await
-                .expect("Failed to get device name")
-                .as_string()
-                .expect("Failed to convert device name to string")
-                .to_string();
-
-            DeviceInfo {
-                name: device_name,
-                id: device_uid,
-                uuid: d.udid.clone(),
-                connection_type,
-            }
+                .ok()
+                .and_then(|v| v.as_boolean());
+        }
+    }
+
+    if !details.paired {
+        details
+            .warnings
+            .push("This computer is not trusted by the device".to_string());
+    }
+    let major_version = details
+        .product_version
+        .as_deref()
+        .and_then(|v| v.split('.').next())
+        .and_then(|v| v.parse::<u32>().ok());
+    if major_version.is_some_and(|v| v >= 16) && details.developer_mode_enabled == Some(false) {
+        details.warnings.push(
+            "Developer Mode is off, sideloaded apps will not open until it is enabled in Settings > Privacy & Security".to_string(),
+        );
+    }
+
+    Ok(details)
+}
+
+async fn lockdown_string(lockdown_client: &mut LockdownClient, key: &str) -> Option<String> {
+    lockdown_client
+        .get_value(Some(key), None)
+        .await
+        .ok()
+        .and_then(|v| v.as_string().map(|s| s.to_string()))
+}
+
+#[tauri::command]
+pub async fn get_device_details(device: DeviceInfo) -> Result<DeviceDetails, String> {
+    device_details(&device).await
+}
+
+#[derive(Clone)]
+pub enum DeviceEvent {
+    Attached(DeviceInfo),
+    Detached(DeviceInfo),
+}
+
+/// Watches usbmuxd for devices being plugged in or removed, reconnecting if usbmuxd goes away.
+///
+/// The usbmuxd listen stream is not `Send`, so this runs on its own thread.
+pub fn spawn_device_watcher(on_event: impl Fn(DeviceEvent) + Send + 'static) {
+    std::thread::Builder::new()
+        .name("device-watcher".to_string())
+        .spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("Failed to build device watcher runtime");
+            runtime.block_on(watch_devices(on_event));
         })
-        .collect();
+        .expect("Failed to spawn device watcher");
+}
 
-    Ok(futures::future::join_all(device_info_futures).await)
+async fn watch_devices(on_event: impl Fn(DeviceEvent)) {
+    let mut attached: HashMap<u32, DeviceInfo> = HashMap::new();
+    loop {
+        if let Err(e) = listen_devices(&mut attached, &on_event).await {
+            warn!("Device watcher disconnected: {}", e);
+        }
+        for (_, device) in attached.drain() {
+            on_event(DeviceEvent::Detached(device));
+        }
+        tokio::time::sleep(Duration::from_secs(5)).await;
+    }
+}
+
+async fn listen_devices(
+    attached: &mut HashMap<u32, DeviceInfo>,
+    on_event: &impl Fn(DeviceEvent),
+) -> Result<(), String> {
+    let mut usbmuxd = UsbmuxdConnection::default()
+        .await
+        .map_err(|e| format!("Failed to connect to usbmuxd: {}", e))?;
+    let addr = usbmuxd_addr()?;
+    let mut events = usbmuxd
+        .listen()
+        .await
+        .map_err(|e| format!("Failed to listen to usbmuxd: {}", e))?;
+
+    while let Some(event) = events.next().await {
+        match event.map_err(|e| format!("Failed to read usbmuxd event: {}", e))? {
+            UsbmuxdListenEvent::Connected(d) => {
+                let device = device_info(&d, addr.clone()).await;
+                debug!("Device attached: {} ({})", device.name, device.uuid);
+                attached.insert(device.id, device.clone());
+                on_event(DeviceEvent::Attached(device));
+            }
+            UsbmuxdListenEvent::Disconnected(id) => {
+                if let Some(device) = attached.remove(&id) {
+                    debug!("Device detached: {} ({})", device.name, device.uuid);
+                    on_event(DeviceEvent::Detached(device));
+                }
+            }
+        }
+    }
+    Err("usbmuxd closed the connection".to_string())
 }
 
 #[tauri::command]
 pub async fn set_selected_device(
-    device_state: State<'_, DeviceInfoMutex>,
+    core: State<'_, IloaderCore>,
     device: Option<DeviceInfo>,
 ) -> Result<(), String> {
-    let mut device_state = device_state.lock().unwrap();
-    *device_state = device;
+    core.select_device(device);
     Ok(())
 }
 
-pub async fn get_provider(device_info: &DeviceInfo) -> Result<UsbmuxdProvider, String> {
+pub fn selected_device(device_state: &DeviceInfoMutex) -> Result<DeviceInfo, String> {
+    let device_lock = device_state.lock().unwrap();
+    match &*device_lock {
+        Some(d) => Ok(d.clone()),
+        None => Err("No device selected".to_string()),
+    }
+}
+
+/// Reaches a device either through usbmuxd or directly over TCP.
+#[derive(Debug)]
+pub enum DeviceProvider {
+    Usbmuxd(UsbmuxdProvider),
+    Tcp(TcpProvider),
+}
+
+impl IdeviceProvider for DeviceProvider {
+    fn connect(
+        &self,
+        port: u16,
+    ) -> Pin<Box<dyn Future<Output = Result<Idevice, IdeviceError>> + Send>> {
+        match self {
+            DeviceProvider::Usbmuxd(p) => p.connect(port),
+            DeviceProvider::Tcp(p) => p.connect(port),
+        }
+    }
+
+    fn label(&self) -> &str {
+        match self {
+            DeviceProvider::Usbmuxd(p) => p.label(),
+            DeviceProvider::Tcp(p) => p.label(),
+        }
+    }
+
+    fn get_pairing_file(
+        &self,
+    ) -> Pin<Box<dyn Future<Output = Result<PairingFile, IdeviceError>> + Send>> {
+        match self {
+            DeviceProvider::Usbmuxd(p) => p.get_pairing_file(),
+            DeviceProvider::Tcp(p) => p.get_pairing_file(),
+        }
+    }
+}
+
+pub async fn get_provider(device_info: &DeviceInfo) -> Result<DeviceProvider, String> {
+    if let Some(network) = &device_info.network {
+        return Ok(DeviceProvider::Tcp(
+            tcp_provider(network, &device_info.uuid).await?,
+        ));
+    }
+
     let mut usbmuxd = UsbmuxdConnection::default()
         .await
         .map_err(|e| format!("Failed to connect to usbmuxd: {}", e))?;
 
-    get_provider_from_connection(device_info, &mut usbmuxd).await
+    Ok(DeviceProvider::Usbmuxd(
+        get_provider_from_connection(device_info, &mut usbmuxd).await?,
+    ))
 }
 
 pub async fn get_provider_from_connection(
@@ -106,6 +405,6 @@ pub async fn get_provider_from_connection(
         .await
         .map_err(|e| format!("Failed to get device: {}", e))?;
 
-    let provider = device.to_provider(UsbmuxdAddr::from_env_var().unwrap(), "iloader");
+    let provider = device.to_provider(usbmuxd_addr()?, "iloader");
     Ok(provider)
 }
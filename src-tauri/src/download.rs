@@ -0,0 +1,894 @@
+use std::{
+    fs::{File, OpenOptions, TryLockError},
+    io::{Read, Seek, Write},
+    path::{Path, PathBuf},
+    sync::OnceLock,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use reqwest::{
+    Certificate, Client, Proxy, Response, StatusCode,
+    header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, IF_RANGE, LAST_MODIFIED, RANGE},
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::State;
+use tokio::io::AsyncWriteExt;
+use tracing::{debug, warn};
+
+use crate::{iloader_core::IloaderCore, ipa::check_ipa};
+
+const CACHE_DIR: &str = "download_cache";
+const CACHE_INDEX: &str = "index.json";
+/// The proxy given to [`export_proxy_env`], which isideload's clients keep using until restart
+static EXPORTED_PROXY: OnceLock<String> = OnceLock::new();
+/// Once the cache is larger than this, the least recently used downloads are removed.
+pub const CACHE_SIZE_LIMIT: u64 = 1024 * 1024 * 1024;
+/// Attempts per download before giving up, waiting twice as long after every failure.
+const MAX_ATTEMPTS: u32 = 5;
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct CacheEntry {
+    url: String,
+    /// The URL after redirects
+    resolved_url: String,
+    /// File name in the cache directory
+    file: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    size: u64,
+    /// Hex-encoded SHA-256 of the file, trusted while its size and `modified` still match
+    #[serde(default)]
+    sha256: Option<String>,
+    /// Unix time the file was last modified when `sha256` was recorded
+    #[serde(default)]
+    modified: Option<u64>,
+    /// Unix time of the last download or cache hit, for eviction
+    last_used: u64,
+}
+
+/// Settings for the HTTP clients iloader creates itself.
+///
+/// isideload builds its own clients for anisette and Apple's servers, those only get the proxy
+/// through [`export_proxy_env`] and trust the system's root certificates. A proxy changed while
+/// iloader runs only reaches them after a restart, see [`exported_proxy`].
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HttpConfig {
+    pub connect_timeout_secs: u64,
+    /// Longest wait for more data from a response before the attempt is given up
+    pub read_timeout_secs: u64,
+    /// `http://`, `https://` or `socks5://` proxy URL, credentials can be given as
+    /// `user:password@host`
+    pub proxy: Option<String>,
+    /// PEM or DER files with root certificates to trust in addition to the system's
+    pub extra_ca_certs: Vec<PathBuf>,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        HttpConfig {
+            connect_timeout_secs: 15,
+            read_timeout_secs: 30,
+            proxy: None,
+            extra_ca_certs: vec![],
+        }
+    }
+}
+
+impl HttpConfig {
+    pub fn client(&self) -> Result<Client, String> {
+        let mut builder = Client::builder()
+            .connect_timeout(Duration::from_secs(self.connect_timeout_secs))
+            .read_timeout(Duration::from_secs(self.read_timeout_secs))
+            .user_agent("iloader");
+        if let Some(proxy) = self.proxy() {
+            builder = builder
+                .proxy(Proxy::all(proxy).map_err(|e| format!("Invalid proxy {}: {}", proxy, e))?);
+        }
+        for path in &self.extra_ca_certs {
+            builder = builder.tls_certs_merge(load_certificates(path)?);
+        }
+        builder
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e))
+    }
+
+    fn proxy(&self) -> Option<&str> {
+        self.proxy
+            .as_deref()
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+    }
+}
+
+fn load_certificates(path: &Path) -> Result<Vec<Certificate>, String> {
+    let contents = std::fs::read(path)
+        .map_err(|e| format!("Failed to read certificate {}: {}", path.display(), e))?;
+    let certs = if contents.windows(10).any(|w| w == b"-----BEGIN") {
+        Certificate::from_pem_bundle(&contents)
+    } else {
+        Certificate::from_der(&contents).map(|cert| vec![cert])
+    };
+    certs.map_err(|e| format!("Invalid certificate {}: {}", path.display(), e))
+}
+
+/// Points HTTP clients iloader doesn't build itself at `proxy` through `HTTP_PROXY` and
+/// `HTTPS_PROXY`, which reqwest reads when a client is created.
+///
+/// # Safety
+///
+/// This modifies the environment, so it must be called before any other thread is started.
+pub unsafe fn export_proxy_env(proxy: &str) {
+    for var in ["HTTP_PROXY", "HTTPS_PROXY"] {
+        unsafe { std::env::set_var(var, proxy) };
+    }
+    let _ = EXPORTED_PROXY.set(proxy.to_string());
+}
+
+/// The proxy isideload's clients use, if one was given to [`export_proxy_env`] at startup.
+pub fn exported_proxy() -> Option<String> {
+    EXPORTED_PROXY.get().cloned()
+}
+
+/// The proxy saved in the app's preferences, for [`export_proxy_env`] before the app starts.
+pub fn saved_proxy() -> Option<String> {
+    let path = dirs::data_dir()?
+        .join("me.nabdev.iloader")
+        .join("preferences.json");
+    let preferences: serde_json::Value = serde_json::from_slice(&std::fs::read(path).ok()?).ok()?;
+    HttpConfig {
+        proxy: preferences["proxy"].as_str().map(|p| p.to_string()),
+        ..Default::default()
+    }
+    .proxy()
+    .map(|p| p.to_string())
+}
+
+/// A file from the download cache, hard-linked (or copied) out of it so evicting or replacing
+/// the cached file doesn't affect it. Removed when dropped.
+pub struct CachedDownload {
+    pub path: PathBuf,
+    /// Hex-encoded SHA-256 of the file
+    pub sha256: String,
+}
+
+impl Drop for CachedDownload {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Response headers used for conditional requests and to resume with `If-Range`.
+#[derive(Clone, Default)]
+struct Validators {
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+impl Validators {
+    fn from_response(response: &Response) -> Self {
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| v.to_string())
+        };
+        Validators {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        }
+    }
+
+    fn if_range(&self) -> Option<&String> {
+        self.etag.as_ref().or(self.last_modified.as_ref())
+    }
+}
+
+enum Fetched {
+    /// The server confirmed the cached copy is current
+    NotModified,
+    Downloaded {
+        resolved_url: String,
+        validators: Validators,
+    },
+}
+
+struct FetchError {
+    message: String,
+    /// Timeouts, dropped connections and server errors, which are worth retrying
+    transient: bool,
+}
+
+impl From<reqwest::Error> for FetchError {
+    fn from(e: reqwest::Error) -> Self {
+        FetchError {
+            transient: e.is_timeout() || e.is_connect() || e.is_request() || e.is_body(),
+            message: e.to_string(),
+        }
+    }
+}
+
+impl FetchError {
+    fn io(e: std::io::Error) -> Self {
+        FetchError {
+            message: format!("Failed to write file: {}", e),
+            transient: false,
+        }
+    }
+}
+
+/// What a `.part` file in the cache was downloaded from, saved next to it so a later download
+/// of the same URL can resume it with `If-Range`.
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PartInfo {
+    url: String,
+    resolved_url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+/// A partial download. Kept after cancellation and errors if it can be resumed, so the next
+/// download of the URL continues it, otherwise removed when dropped.
+struct PartFile {
+    path: PathBuf,
+    /// The locked [`PartInfo`] file of a download that can be resumed, and its path
+    info: Option<(File, PathBuf)>,
+}
+
+impl PartFile {
+    /// A partial download that is removed when dropped.
+    fn temporary(path: PathBuf) -> Self {
+        PartFile { path, info: None }
+    }
+
+    /// The partial download of `url` in `cache_dir`, locked against other downloads of it.
+    /// Falls back to a temporary one if another download of `url` is running.
+    fn for_url(cache_dir: &Path, url: &str) -> Result<Self, String> {
+        let key = &hex::encode(Sha256::digest(url.as_bytes()))[..32];
+        let info_path = cache_dir.join(format!("{}.part.json", key));
+        let info = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .read(true)
+            .write(true)
+            .open(&info_path)
+            .map_err(|e| format!("Failed to open partial download: {}", e))?;
+        match info.try_lock() {
+            Ok(()) => Ok(PartFile {
+                path: cache_dir.join(format!("{}.part", key)),
+                info: Some((info, info_path)),
+            }),
+            Err(TryLockError::WouldBlock) => Ok(PartFile::temporary(
+                cache_dir.join(format!("{}.part", uuid::Uuid::new_v4())),
+            )),
+            Err(TryLockError::Error(e)) => Err(format!("Failed to lock partial download: {}", e)),
+        }
+    }
+
+    /// How much of `url` was downloaded before, and the response it came from.
+    fn resume_state(&mut self, url: &str) -> (u64, Option<(String, Validators)>) {
+        let Some((info, _)) = &mut self.info else {
+            return (0, None);
+        };
+        let mut contents = String::new();
+        let saved = info
+            .read_to_string(&mut contents)
+            .ok()
+            .and_then(|_| serde_json::from_str::<PartInfo>(&contents).ok())
+            .filter(|saved| {
+                saved.url == url && (saved.etag.is_some() || saved.last_modified.is_some())
+            });
+        let done = std::fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0);
+        match saved {
+            Some(saved) if done > 0 => (
+                done,
+                Some((
+                    saved.resolved_url,
+                    Validators {
+                        etag: saved.etag,
+                        last_modified: saved.last_modified,
+                    },
+                )),
+            ),
+            _ => (0, None),
+        }
+    }
+
+    /// Records the response being written, once it replaces what was downloaded before.
+    fn save(&mut self, url: &str, resolved_url: &str, validators: &Validators) {
+        let Some((info, _)) = &mut self.info else {
+            return;
+        };
+        let saved = PartInfo {
+            url: url.to_string(),
+            resolved_url: resolved_url.to_string(),
+            etag: validators.etag.clone(),
+            last_modified: validators.last_modified.clone(),
+        };
+        let result = serde_json::to_vec(&saved)
+            .map_err(std::io::Error::other)
+            .and_then(|contents| {
+                info.set_len(0)?;
+                info.rewind()?;
+                info.write_all(&contents)
+            });
+        if let Err(e) = result {
+            warn!("Failed to save partial download info: {}", e);
+        }
+    }
+
+    /// Moves the finished download to `dest`.
+    async fn finish(mut self, dest: &Path) -> Result<(), String> {
+        tokio::fs::rename(&self.path, dest)
+            .await
+            .map_err(|e| format!("Failed to save download: {}", e))?;
+        if let Some((_, info_path)) = self.info.take() {
+            let _ = std::fs::remove_file(info_path);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for PartFile {
+    fn drop(&mut self) {
+        let resumable = std::fs::metadata(&self.path).is_ok_and(|m| m.len() > 0);
+        match &self.info {
+            Some(_) if resumable => {}
+            Some((_, info_path)) => {
+                let _ = std::fs::remove_file(&self.path);
+                let _ = std::fs::remove_file(info_path);
+            }
+            None => {
+                let _ = std::fs::remove_file(&self.path);
+            }
+        }
+    }
+}
+
+/// Streams `url` to `dest` through a `.part` file, calling `on_progress(done, total)` as bytes
+/// arrive. See [`fetch`] for retries.
+pub async fn download(
+    client: &Client,
+    url: &str,
+    dest: &Path,
+    mut on_progress: impl FnMut(u64, Option<u64>),
+) -> Result<(), String> {
+    let mut part = PartFile::temporary(dest.with_extension("part"));
+    fetch(client, url, &mut part, None, &mut on_progress)
+        .await
+        .map_err(|e| format!("Failed to download file: {}", e.message))?;
+    part.finish(dest).await
+}
+
+/// Downloads `url` to `part`, retrying transient failures with exponential backoff. Once data
+/// has arrived, retries resume with a `Range` request where the server allows it, as does the
+/// first request if `part` has data left from an earlier download.
+///
+/// If `cached` is given and there is nothing to resume, the first request is conditional and
+/// may return [`Fetched::NotModified`].
+async fn fetch(
+    client: &Client,
+    url: &str,
+    part: &mut PartFile,
+    cached: Option<&Validators>,
+    on_progress: &mut impl FnMut(u64, Option<u64>),
+) -> Result<Fetched, FetchError> {
+    let (mut done, mut started) = part.resume_state(url);
+    let mut attempt = 1;
+    loop {
+        let error = match fetch_attempt(
+            client,
+            url,
+            part,
+            cached,
+            &mut done,
+            &mut started,
+            on_progress,
+        )
+        .await
+        {
+            Ok(fetched) => return Ok(fetched),
+            Err(e) if !e.transient || attempt >= MAX_ATTEMPTS => return Err(e),
+            Err(e) => e,
+        };
+
+        let delay = RETRY_BASE_DELAY * 2u32.pow(attempt - 1);
+        warn!(
+            "Download of {} failed (attempt {} of {}), retrying in {}s: {}",
+            url,
+            attempt,
+            MAX_ATTEMPTS,
+            delay.as_secs(),
+            error.message
+        );
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+/// One request of [`fetch`]. `done` and `started` (the resolved URL and validators of the
+/// response being written) carry over between attempts so they can resume.
+async fn fetch_attempt(
+    client: &Client,
+    url: &str,
+    part: &mut PartFile,
+    cached: Option<&Validators>,
+    done: &mut u64,
+    started: &mut Option<(String, Validators)>,
+    on_progress: &mut impl FnMut(u64, Option<u64>),
+) -> Result<Fetched, FetchError> {
+    let mut request = client.get(url);
+    let resume_from = started.as_ref().and_then(|(_, v)| v.if_range());
+    match (resume_from, cached) {
+        (Some(validator), _) if *done > 0 => {
+            request = request
+                .header(RANGE, format!("bytes={}-", done))
+                .header(IF_RANGE, validator);
+        }
+        (_, Some(cached)) if started.is_none() => {
+            if let Some(etag) = &cached.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &cached.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+        _ => {}
+    }
+
+    let mut response = request.send().await?;
+    let status = response.status();
+    if status == StatusCode::NOT_MODIFIED && started.is_none() && cached.is_some() {
+        return Ok(Fetched::NotModified);
+    }
+    if status == StatusCode::RANGE_NOT_SATISFIABLE && *done > 0 {
+        // The partial download was already complete, or the file got shorter
+        *done = 0;
+        *started = None;
+        return Err(FetchError {
+            message: format!("HTTP {}", status),
+            transient: true,
+        });
+    }
+    if !status.is_success() {
+        return Err(FetchError {
+            message: format!("HTTP {}", status),
+            transient: status.is_server_error()
+                || status == StatusCode::TOO_MANY_REQUESTS
+                || status == StatusCode::REQUEST_TIMEOUT,
+        });
+    }
+
+    // The server sends the whole file again if it can't resume or the file changed
+    let append = *done > 0 && status == StatusCode::PARTIAL_CONTENT;
+    if !append {
+        *done = 0;
+        let validators = Validators::from_response(&response);
+        part.save(url, response.url().as_str(), &validators);
+        *started = Some((response.url().to_string(), validators));
+    } else {
+        debug!("Resuming download of {} at {} bytes", url, done);
+    }
+    let total = response.content_length().map(|len| len + *done);
+
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(append)
+        .truncate(!append)
+        .open(&part.path)
+        .await
+        .map_err(FetchError::io)?;
+    on_progress(*done, total);
+    loop {
+        let chunk = match response.chunk().await {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => break,
+            Err(e) => {
+                // Keep what was written so the next attempt can resume from it
+                file.flush().await.map_err(FetchError::io)?;
+                return Err(e.into());
+            }
+        };
+        file.write_all(&chunk).await.map_err(FetchError::io)?;
+        *done += chunk.len() as u64;
+        on_progress(*done, total);
+    }
+    file.flush().await.map_err(FetchError::io)?;
+
+    let (resolved_url, validators) = started.clone().unwrap_or_default();
+    Ok(Fetched::Downloaded {
+        resolved_url,
+        validators,
+    })
+}
+
+/// Hex-encoded SHA-256 of the file at `path`.
+pub async fn sha256_file(path: &Path) -> Result<String, String> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let mut file = std::fs::File::open(&path).map_err(|e| e.to_string())?;
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut file, &mut hasher).map_err(|e| e.to_string())?;
+        Ok(hex::encode(hasher.finalize()))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e: String| format!("Failed to hash file: {}", e))
+}
+
+/// Looks up the SHA-256 GitHub publishes for a release asset, given its download URL
+/// (`https://github.com/<owner>/<repo>/releases/download/<tag>/<asset>`, or
+/// `.../releases/latest/download/<asset>`).
+///
+/// Returns `None` for other URLs and for assets uploaded before GitHub started recording digests.
+pub async fn published_sha256(client: &Client, url: &str) -> Result<Option<String>, String> {
+    let Some(path) = url.strip_prefix("https://github.com/") else {
+        return Ok(None);
+    };
+    let parts: Vec<&str> = path.split('/').collect();
+    let (owner, repo, release, asset) = match parts[..] {
+        [owner, repo, "releases", "latest", "download", asset] => {
+            (owner, repo, "latest".to_string(), asset)
+        }
+        [owner, repo, "releases", "download", tag, asset] => {
+            (owner, repo, format!("tags/{}", tag), asset)
+        }
+        _ => return Ok(None),
+    };
+
+    let release: serde_json::Value = client
+        .get(format!(
+            "https://api.github.com/repos/{}/{}/releases/{}",
+            owner, repo, release
+        ))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| format!("Failed to fetch release info: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse release info: {}", e))?;
+
+    Ok(release["assets"]
+        .as_array()
+        .and_then(|assets| assets.iter().find(|a| a["name"] == asset))
+        .and_then(|a| a["digest"].as_str())
+        .and_then(|d| d.strip_prefix("sha256:"))
+        .map(|d| d.to_lowercase()))
+}
+
+/// Removes the entries for `url` from the index, returning their files.
+fn remove_entries(entries: &mut Vec<CacheEntry>, url: &str) -> Vec<String> {
+    let mut removed = Vec::new();
+    entries.retain(|e| {
+        if e.url == url {
+            removed.push(e.file.clone());
+            false
+        } else {
+            true
+        }
+    });
+    removed
+}
+
+/// Unix time the file at `path` was last modified, and its size.
+fn file_stamp(path: &Path) -> Option<(u64, u64)> {
+    let metadata = std::fs::metadata(path).ok()?;
+    let modified = metadata
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_secs();
+    Some((modified, metadata.len()))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl IloaderCore {
+    /// Downloads `url` through the cache and checks that it is an intact IPA.
+    ///
+    /// The file must match `sha256` if given, otherwise the digest GitHub publishes for the
+    /// release asset when there is one. A file that fails the checks is removed from the cache.
+    pub async fn download_ipa(
+        &self,
+        url: &str,
+        sha256: Option<&str>,
+        on_progress: impl FnMut(u64, Option<u64>),
+    ) -> Result<CachedDownload, String> {
+        let download = self
+            .cached_download(url, sha256.is_some(), on_progress)
+            .await?;
+
+        let expected = match sha256 {
+            Some(sha256) => Some(sha256.trim().to_lowercase()),
+            None => published_sha256(&self.http_client()?, url)
+                .await
+                .unwrap_or_else(|e| {
+                    warn!("Skipping checksum verification: {}", e);
+                    None
+                }),
+        };
+        if let Some(expected) = expected
+            && expected != download.sha256
+        {
+            self.remove_cached(url)?;
+            return Err(format!(
+                "Checksum mismatch: expected SHA-256 {}, got {}. The download was corrupted or tampered with",
+                expected, download.sha256
+            ));
+        }
+
+        let path = download.path.clone();
+        let valid = tokio::task::spawn_blocking(move || check_ipa(&path))
+            .await
+            .map_err(|e| e.to_string())?;
+        if let Err(e) = valid {
+            self.remove_cached(url)?;
+            return Err(format!("The downloaded file is not a valid IPA: {}", e));
+        }
+
+        Ok(download)
+    }
+
+    /// Whether `path` is a download in the cache, which may be evicted or replaced.
+    pub(crate) fn is_cached_download(&self, path: &Path) -> bool {
+        path.starts_with(self.data_dir.join(CACHE_DIR))
+    }
+
+    /// Downloads `url` into the cache in the data directory and returns a link to the cached
+    /// file, see [`CachedDownload`].
+    ///
+    /// A cached copy is revalidated with `If-None-Match`/`If-Modified-Since` and reused if the
+    /// server says it hasn't changed, or if the server can't be reached.
+    ///
+    /// The cached copy is only hashed again if it changed on disk since it was recorded, or if
+    /// `verify` is set, e.g. because the caller checks it against a digest it was given.
+    pub async fn cached_download(
+        &self,
+        url: &str,
+        verify: bool,
+        on_progress: impl FnMut(u64, Option<u64>),
+    ) -> Result<CachedDownload, String> {
+        let cache_dir = self.data_dir.join(CACHE_DIR);
+        let mut cached = self
+            .cache_entries()?
+            .into_iter()
+            .find(|e| e.url == url && cache_dir.join(&e.file).exists());
+        if let Some(entry) = &mut cached {
+            let path = cache_dir.join(&entry.file);
+            let unchanged = entry.sha256.is_some()
+                && entry
+                    .modified
+                    .is_some_and(|modified| file_stamp(&path) == Some((modified, entry.size)));
+            if verify || !unchanged {
+                let sha256 = sha256_file(&path).await?;
+                if entry.sha256.as_ref().is_some_and(|s| *s != sha256) {
+                    warn!(
+                        "Cached download of {} is corrupted, downloading it again",
+                        url
+                    );
+                    cached = None;
+                } else {
+                    entry.sha256 = Some(sha256);
+                }
+            }
+        }
+
+        tokio::fs::create_dir_all(&cache_dir)
+            .await
+            .map_err(|e| format!("Failed to create cache directory: {}", e))?;
+        let id = uuid::Uuid::new_v4();
+        let mut part = PartFile::for_url(&cache_dir, url)?;
+        let validators = cached.as_ref().map(|e| Validators {
+            etag: e.etag.clone(),
+            last_modified: e.last_modified.clone(),
+        });
+        let mut on_progress = on_progress;
+        let fetched = fetch(
+            &self.http_client()?,
+            url,
+            &mut part,
+            validators.as_ref(),
+            &mut on_progress,
+        )
+        .await;
+
+        let (resolved_url, validators) = match (fetched, cached) {
+            (Ok(Fetched::NotModified), Some(entry)) => {
+                debug!("{} has not changed, using the cached download", url);
+                return self.use_cached(entry).await;
+            }
+            (Ok(Fetched::NotModified), None) => {
+                return Err("Failed to download file: unexpected HTTP 304".to_string());
+            }
+            (
+                Ok(Fetched::Downloaded {
+                    resolved_url,
+                    validators,
+                }),
+                _,
+            ) => (resolved_url, validators),
+            (Err(e), Some(entry)) if e.transient => {
+                warn!(
+                    "Failed to download {}, using the cached download: {}",
+                    url, e.message
+                );
+                return self.use_cached(entry).await;
+            }
+            (Err(e), _) => return Err(format!("Failed to download file: {}", e.message)),
+        };
+
+        let file_name = resolved_url
+            .rsplit('/')
+            .next()
+            .and_then(|n| n.split('?').next())
+            .filter(|n| !n.is_empty())
+            .unwrap_or("download");
+        let file = format!("{}-{}", id, file_name);
+        let dest = cache_dir.join(&file);
+        part.finish(&dest).await?;
+        let (modified, size) =
+            file_stamp(&dest).ok_or_else(|| "Failed to save download".to_string())?;
+        let sha256 = sha256_file(&dest).await?;
+
+        // Linked under the cache lock, before anything else can evict the file
+        let mut linked = Err("Download was not saved".to_string());
+        self.update_cache(|entries| {
+            linked = self.link_out(&file);
+            let stale = remove_entries(entries, url);
+            entries.push(CacheEntry {
+                url: url.to_string(),
+                resolved_url: resolved_url.clone(),
+                file,
+                etag: validators.etag,
+                last_modified: validators.last_modified,
+                size,
+                sha256: Some(sha256.clone()),
+                modified: Some(modified),
+                last_used: now(),
+            });
+            stale
+        })?;
+
+        Ok(CachedDownload {
+            path: linked?,
+            sha256,
+        })
+    }
+
+    /// Removes every cached download.
+    pub fn clear_cache(&self) -> Result<(), String> {
+        let _lock = self.cache_lock.lock().unwrap();
+        let cache_dir = self.data_dir.join(CACHE_DIR);
+        if cache_dir.exists() {
+            std::fs::remove_dir_all(&cache_dir)
+                .map_err(|e| format!("Failed to clear cache: {}", e))?;
+        }
+        Ok(())
+    }
+
+    async fn use_cached(&self, entry: CacheEntry) -> Result<CachedDownload, String> {
+        let path = self.data_dir.join(CACHE_DIR).join(&entry.file);
+        let sha256 = match entry.sha256 {
+            Some(sha256) => sha256,
+            None => sha256_file(&path).await?,
+        };
+        let mut linked = Err("Cached download was not found".to_string());
+        self.update_cache(|entries| {
+            if let Some(e) = entries.iter_mut().find(|e| e.url == entry.url) {
+                linked = self.link_out(&e.file);
+                e.last_used = now();
+                e.sha256 = Some(sha256.clone());
+                if let Some((modified, size)) = file_stamp(&path) {
+                    e.modified = Some(modified);
+                    e.size = size;
+                }
+            }
+            vec![]
+        })?;
+        Ok(CachedDownload {
+            path: linked?,
+            sha256,
+        })
+    }
+
+    /// Hard-links `file` from the cache into the temp directory, or copies it if it can't be
+    /// linked. Call with the cache lock held so the file can't be evicted meanwhile.
+    fn link_out(&self, file: &str) -> Result<PathBuf, String> {
+        let dir = self.temp_dir.join("downloads");
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create download directory: {}", e))?;
+        let source = self.data_dir.join(CACHE_DIR).join(file);
+        let dest = dir.join(format!("{}-{}", uuid::Uuid::new_v4(), file));
+        if std::fs::hard_link(&source, &dest).is_err() {
+            std::fs::copy(&source, &dest)
+                .map_err(|e| format!("Failed to use cached download: {}", e))?;
+        }
+        Ok(dest)
+    }
+
+    fn remove_cached(&self, url: &str) -> Result<(), String> {
+        self.update_cache(|entries| remove_entries(entries, url))
+    }
+
+    fn cache_entries(&self) -> Result<Vec<CacheEntry>, String> {
+        let _lock = self.cache_lock.lock().unwrap();
+        self.read_cache_index()
+    }
+
+    /// Applies `update` to the index, which returns files to delete, then evicts the least
+    /// recently used entries until the cache fits in [`CACHE_SIZE_LIMIT`].
+    fn update_cache(
+        &self,
+        update: impl FnOnce(&mut Vec<CacheEntry>) -> Vec<String>,
+    ) -> Result<(), String> {
+        let _lock = self.cache_lock.lock().unwrap();
+        let cache_dir = self.data_dir.join(CACHE_DIR);
+        let mut entries = self.read_cache_index()?;
+        let mut remove = update(&mut entries);
+
+        entries.sort_by_key(|e| std::cmp::Reverse(e.last_used));
+        let mut size = 0;
+        let mut first = true;
+        entries.retain(|e| {
+            size += e.size;
+            // Always keep the most recently used entry, it is about to be installed
+            let keep = first || size <= CACHE_SIZE_LIMIT;
+            first = false;
+            if !keep {
+                remove.push(e.file.clone());
+            }
+            keep
+        });
+
+        for file in remove {
+            if let Err(e) = std::fs::remove_file(cache_dir.join(&file)) {
+                warn!("Failed to remove cached download {}: {}", file, e);
+            }
+        }
+
+        std::fs::create_dir_all(&cache_dir)
+            .map_err(|e| format!("Failed to create cache directory: {}", e))?;
+        let contents = serde_json::to_vec_pretty(&entries)
+            .map_err(|e| format!("Failed to serialize cache index: {}", e))?;
+        std::fs::write(cache_dir.join(CACHE_INDEX), contents)
+            .map_err(|e| format!("Failed to save cache index: {}", e))
+    }
+
+    fn read_cache_index(&self) -> Result<Vec<CacheEntry>, String> {
+        let path = self.data_dir.join(CACHE_DIR).join(CACHE_INDEX);
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+        let contents =
+            std::fs::read(&path).map_err(|e| format!("Failed to read cache index: {}", e))?;
+        serde_json::from_slice(&contents).map_err(|e| format!("Failed to parse cache index: {}", e))
+    }
+}
+
+#[tauri::command]
+pub fn set_http_config(core: State<'_, IloaderCore>, config: HttpConfig) {
+    core.set_http_config(config);
+}
+
+#[tauri::command]
+pub fn get_exported_proxy() -> Option<String> {
+    exported_proxy()
+}
+
+#[tauri::command]
+pub fn clear_cache(core: State<'_, IloaderCore>) -> Result<(), String> {
+    core.clear_cache()
+}
@@ -0,0 +1,230 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
+use chrono::{DateTime, Utc};
+use idevice::{IdeviceService, installation_proxy::InstallationProxyClient};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use tracing::warn;
+
+use crate::{
+    device::{DeviceInfo, get_provider},
+    iloader_core::IloaderCore,
+    ipa::AppOverrides,
+    jobs::JobKind,
+    sideload::SigningInfo,
+};
+
+const INSTALLED_APPS_FILE: &str = "installed_apps.json";
+
+/// An app iloader signed and installed, tracked until it is uninstalled so it can be refreshed
+/// before its provisioning profile expires.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct InstalledApp {
+    /// The bundle ID on the device, which includes the team ID
+    pub bundle_id: String,
+    pub udid: String,
+    pub team_id: String,
+    pub certificate_serials: Vec<String>,
+    pub expires_at: DateTime<Utc>,
+    pub installed_at: DateTime<Utc>,
+    /// The IPA or app bundle that was signed
+    pub app_path: String,
+    #[serde(default)]
+    pub overrides: AppOverrides,
+    /// Installs the app again from where it came from, e.g. its URL rather than `app_path` in
+    /// the download cache. `None` for apps tracked before this was recorded.
+    #[serde(default)]
+    pub origin: Option<JobKind>,
+}
+
+/// An [`InstalledApp`] still on its device, or on a device that isn't connected.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ExpiringApp {
+    #[serde(flatten)]
+    pub app: InstalledApp,
+    /// The name on the home screen, `None` if the device isn't connected
+    pub name: Option<String>,
+    /// Whether the app has stopped launching
+    pub expired: bool,
+    /// Days until the app stops launching, rounded up, 0 once it has
+    pub days_remaining: i64,
+    /// Hours until the app stops launching, rounded up, 0 once it has
+    pub hours_remaining: i64,
+}
+
+impl IloaderCore {
+    /// The apps iloader installed, soonest to expire first. Apps no longer installed on a
+    /// connected device are forgotten.
+    pub async fn installed_apps(&self) -> Result<Vec<ExpiringApp>, String> {
+        let tracked = {
+            let _lock = self.installed_apps_lock.lock().unwrap();
+            self.saved_installed_apps()?
+        };
+        let udids: HashSet<&str> = tracked.iter().map(|a| a.udid.as_str()).collect();
+
+        // Bundle IDs and names of the user apps on each connected device with tracked apps
+        let mut on_device: HashMap<String, HashMap<String, Option<String>>> = HashMap::new();
+        // Without usbmuxd nothing is connected, the tracked apps are still listed
+        let devices = self.devices().await.unwrap_or_else(|e| {
+            warn!("Failed to list devices: {}", e);
+            Vec::new()
+        });
+        for device in devices {
+            if !udids.contains(device.uuid.as_str()) {
+                continue;
+            }
+            match device_apps(&device).await {
+                Ok(apps) => {
+                    on_device.insert(device.uuid.clone(), apps);
+                }
+                Err(e) => warn!("Failed to list apps on {}: {}", device.name, e),
+            }
+        }
+        let is_uninstalled = |app: &InstalledApp| {
+            on_device
+                .get(&app.udid)
+                .is_some_and(|apps| !apps.contains_key(&app.bundle_id))
+        };
+
+        if tracked.iter().any(is_uninstalled) {
+            let _lock = self.installed_apps_lock.lock().unwrap();
+            // Reload in case an install finished while the devices were queried
+            let mut apps = self.saved_installed_apps()?;
+            apps.retain(|app| !is_uninstalled(app));
+            self.save_installed_apps(&apps)?;
+        }
+
+        let now = Utc::now();
+        let mut apps: Vec<ExpiringApp> = tracked
+            .into_iter()
+            .filter(|app| !is_uninstalled(app))
+            .map(|app| {
+                let (days_remaining, hours_remaining) = time_remaining(app.expires_at, now);
+                ExpiringApp {
+                    name: on_device
+                        .get(&app.udid)
+                        .and_then(|apps| apps.get(&app.bundle_id).cloned().flatten()),
+                    expired: app.expires_at <= now,
+                    days_remaining,
+                    hours_remaining,
+                    app,
+                }
+            })
+            .collect();
+        apps.sort_by_key(|a| a.app.expires_at);
+        Ok(apps)
+    }
+
+    /// Starts tracking the app at `app_path`, installed on `device` after being signed with
+    /// `signing`, and reinstalled by running `origin`. Replaces an earlier install of the same
+    /// app on the device.
+    pub(crate) fn track_installed_app(
+        &self,
+        device: &DeviceInfo,
+        app_path: &Path,
+        overrides: &AppOverrides,
+        origin: &JobKind,
+        signing: &SigningInfo,
+    ) -> Result<(), String> {
+        let _lock = self.installed_apps_lock.lock().unwrap();
+        let mut apps = self.saved_installed_apps()?;
+        apps.retain(|a| !(a.udid == device.uuid && a.bundle_id == signing.bundle_id));
+        apps.push(InstalledApp {
+            bundle_id: signing.bundle_id.clone(),
+            udid: device.uuid.clone(),
+            team_id: signing.team_id.clone(),
+            certificate_serials: signing.certificate_serials.clone(),
+            expires_at: signing.expires_at,
+            installed_at: Utc::now(),
+            app_path: app_path.to_string_lossy().to_string(),
+            overrides: overrides.clone(),
+            origin: Some(origin.clone()),
+        });
+        self.save_installed_apps(&apps)
+    }
+
+    fn saved_installed_apps(&self) -> Result<Vec<InstalledApp>, String> {
+        let path = self.data_dir.join(INSTALLED_APPS_FILE);
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+        let contents =
+            std::fs::read(&path).map_err(|e| format!("Failed to read installed apps: {}", e))?;
+        serde_json::from_slice(&contents)
+            .map_err(|e| format!("Failed to parse installed apps: {}", e))
+    }
+
+    fn save_installed_apps(&self, apps: &[InstalledApp]) -> Result<(), String> {
+        std::fs::create_dir_all(&self.data_dir)
+            .map_err(|e| format!("Failed to create data directory: {}", e))?;
+        let contents = serde_json::to_vec_pretty(apps)
+            .map_err(|e| format!("Failed to serialize installed apps: {}", e))?;
+        std::fs::write(self.data_dir.join(INSTALLED_APPS_FILE), contents)
+            .map_err(|e| format!("Failed to save installed apps: {}", e))
+    }
+}
+
+/// The user apps on `device` by bundle ID, with their display names.
+async fn device_apps(device: &DeviceInfo) -> Result<HashMap<String, Option<String>>, String> {
+    let provider = get_provider(device).await?;
+    let mut installation_proxy = InstallationProxyClient::connect(&provider)
+        .await
+        .map_err(|e| format!("Failed to connect to installation proxy: {}", e))?;
+    let installed_apps = installation_proxy
+        .get_apps(Some("User"), None)
+        .await
+        .map_err(|e| format!("Failed to get installed apps: {}", e))?;
+    Ok(installed_apps
+        .into_iter()
+        .map(|(bundle_id, app)| {
+            let name = app
+                .as_dictionary()
+                .and_then(|x| {
+                    x.get("CFBundleDisplayName")
+                        .or_else(|| x.get("CFBundleName"))
+                })
+                .and_then(|x| x.as_string())
+                .map(|x| x.to_string());
+            (bundle_id, name)
+        })
+        .collect())
+}
+
+/// The days and hours left until `expires_at`, each rounded up so an app with a few hours left
+/// isn't shown as having 0 days left.
+fn time_remaining(expires_at: DateTime<Utc>, now: DateTime<Utc>) -> (i64, i64) {
+    let seconds = (expires_at - now).num_seconds().max(0);
+    let round_up = |unit: i64| (seconds + unit - 1) / unit;
+    (round_up(24 * 60 * 60), round_up(60 * 60))
+}
+
+#[tauri::command]
+pub async fn list_installed_apps(core: State<'_, IloaderCore>) -> Result<Vec<ExpiringApp>, String> {
+    core.installed_apps().await
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    #[test]
+    fn time_remaining_rounds_up() {
+        let now = Utc::now();
+        assert_eq!(time_remaining(now + Duration::days(7), now), (7, 168));
+        assert_eq!(
+            time_remaining(now + Duration::days(6) + Duration::minutes(1), now),
+            (7, 145)
+        );
+        assert_eq!(time_remaining(now + Duration::hours(5), now), (1, 5));
+        assert_eq!(time_remaining(now + Duration::seconds(1), now), (1, 1));
+        assert_eq!(time_remaining(now, now), (0, 0));
+        assert_eq!(time_remaining(now - Duration::hours(5), now), (0, 0));
+    }
+}
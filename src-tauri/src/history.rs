@@ -0,0 +1,194 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::{iloader_core::IloaderCore, sideload::SigningInfo};
+
+const HISTORY_FILE: &str = "history.json";
+/// The oldest entries are dropped past this many
+const MAX_HISTORY_ENTRIES: usize = 500;
+
+/// A record of one [`Operation`](crate::operation::Operation), kept after it finishes.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    /// Unique per entry
+    pub id: String,
+    /// What the operation did, e.g. `sideload`
+    pub name: String,
+    /// The [`Operation::id`](crate::operation::Operation::id) its updates were sent under
+    pub operation_id: String,
+    pub state: HistoryState,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// The Apple ID logged in when the operation started
+    pub email: Option<String>,
+    /// UDIDs of the devices the operation targeted
+    pub devices: Vec<String>,
+    /// Paths or URLs of the apps the operation installed
+    pub apps: Vec<String>,
+    /// What each installed app was signed with
+    pub signed: Vec<SigningInfo>,
+    pub steps: Vec<HistoryStep>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum HistoryState {
+    /// Still running, or iloader exited before it finished
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryStep {
+    pub id: String,
+    pub state: HistoryState,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// The `extra_details` the step failed with
+    pub error: Option<String>,
+}
+
+impl HistoryEntry {
+    pub fn new(name: String, operation_id: String, email: Option<String>) -> Self {
+        HistoryEntry {
+            id: uuid::Uuid::new_v4().to_string(),
+            name,
+            operation_id,
+            state: HistoryState::Running,
+            started_at: Utc::now(),
+            finished_at: None,
+            email,
+            devices: Vec::new(),
+            apps: Vec::new(),
+            signed: Vec::new(),
+            steps: Vec::new(),
+        }
+    }
+
+    /// Applies an operation update for step `step_id`.
+    pub fn update_step(&mut self, update_type: &str, step_id: &str, error: Option<&str>) {
+        let state = match update_type {
+            "started" => HistoryState::Running,
+            "finished" => HistoryState::Succeeded,
+            "failed" => HistoryState::Failed,
+            "cancelled" => HistoryState::Cancelled,
+            _ => return,
+        };
+        let now = Utc::now();
+        // Steps can fail or be cancelled without having been started
+        let step = match self.steps.iter().rposition(|s| s.id == step_id) {
+            Some(i) => &mut self.steps[i],
+            None => {
+                self.steps.push(HistoryStep {
+                    id: step_id.to_string(),
+                    state,
+                    started_at: now,
+                    finished_at: None,
+                    error: None,
+                });
+                self.steps.last_mut().unwrap()
+            }
+        };
+        step.state = state;
+        if state == HistoryState::Running {
+            step.started_at = now;
+            step.finished_at = None;
+            step.error = None;
+        } else {
+            step.finished_at = Some(now);
+            step.error = error.map(|e| e.to_string());
+        }
+    }
+
+    /// Sets the final state from the steps: failed or cancelled if any step was, succeeded if
+    /// every step finished.
+    pub fn finish(&mut self) {
+        let states: Vec<HistoryState> = self.steps.iter().map(|s| s.state).collect();
+        self.state = if states.contains(&HistoryState::Failed) {
+            HistoryState::Failed
+        } else if states.contains(&HistoryState::Cancelled) {
+            HistoryState::Cancelled
+        } else if !states.is_empty() && !states.contains(&HistoryState::Running) {
+            HistoryState::Succeeded
+        } else {
+            // Returned an error before reporting a step
+            HistoryState::Failed
+        };
+        self.finished_at = Some(Utc::now());
+    }
+}
+
+impl IloaderCore {
+    /// Every recorded operation, newest first.
+    pub fn history(&self) -> Result<Vec<HistoryEntry>, String> {
+        let _lock = self.history_lock.lock().unwrap();
+        let mut history = self.saved_history()?;
+        history.reverse();
+        Ok(history)
+    }
+
+    pub fn history_entry(&self, id: &str) -> Result<HistoryEntry, String> {
+        let _lock = self.history_lock.lock().unwrap();
+        self.saved_history()?
+            .into_iter()
+            .find(|e| e.id == id)
+            .ok_or_else(|| format!("Unknown history entry {}", id))
+    }
+
+    pub fn clear_history(&self) -> Result<(), String> {
+        let _lock = self.history_lock.lock().unwrap();
+        self.save_history(&[])
+    }
+
+    /// Adds `entry` to the history, or replaces the entry with the same id.
+    pub(crate) fn save_history_entry(&self, entry: &HistoryEntry) -> Result<(), String> {
+        let _lock = self.history_lock.lock().unwrap();
+        let mut history = self.saved_history()?;
+        match history.iter_mut().find(|e| e.id == entry.id) {
+            Some(existing) => *existing = entry.clone(),
+            None => history.push(entry.clone()),
+        }
+        let excess = history.len().saturating_sub(MAX_HISTORY_ENTRIES);
+        history.drain(..excess);
+        self.save_history(&history)
+    }
+
+    fn saved_history(&self) -> Result<Vec<HistoryEntry>, String> {
+        let path = self.data_dir.join(HISTORY_FILE);
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+        let contents =
+            std::fs::read(&path).map_err(|e| format!("Failed to read history: {}", e))?;
+        serde_json::from_slice(&contents).map_err(|e| format!("Failed to parse history: {}", e))
+    }
+
+    fn save_history(&self, history: &[HistoryEntry]) -> Result<(), String> {
+        std::fs::create_dir_all(&self.data_dir)
+            .map_err(|e| format!("Failed to create data directory: {}", e))?;
+        let contents = serde_json::to_vec_pretty(history)
+            .map_err(|e| format!("Failed to serialize history: {}", e))?;
+        std::fs::write(self.data_dir.join(HISTORY_FILE), contents)
+            .map_err(|e| format!("Failed to save history: {}", e))
+    }
+}
+
+#[tauri::command]
+pub fn list_history(core: State<'_, IloaderCore>) -> Result<Vec<HistoryEntry>, String> {
+    core.history()
+}
+
+#[tauri::command]
+pub fn get_history_entry(core: State<'_, IloaderCore>, id: String) -> Result<HistoryEntry, String> {
+    core.history_entry(&id)
+}
+
+#[tauri::command]
+pub fn clear_history(core: State<'_, IloaderCore>) -> Result<(), String> {
+    core.clear_history()
+}
@@ -0,0 +1,230 @@
+use std::{
+    future::Future,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    account::CertificateInfo,
+    device::{DeviceInfo, DeviceInfoMutex, selected_device, usbmuxd_devices},
+    download::HttpConfig,
+    jobs::JobQueue,
+    network::LOCKDOWN_PORT,
+    operation::{Operation, OperationRegistry, ProgressSink},
+    quota::AppIdShortfall,
+    refresh::RefreshConfig,
+    sideload::{SideloaderGuard, SideloaderMutex},
+};
+
+/// Answers the questions iloader needs to ask the user while logging in and installing.
+pub trait Prompter: Send + Sync {
+    /// Returns the 2FA code, or `None` to cancel.
+    fn two_factor_code(&self) -> Option<String>;
+
+    /// Called when no more development certificates can be created. Returns the serial numbers
+    /// of the certificates to revoke, or `None` to cancel.
+    fn certificates_to_revoke(&self, certs: Vec<CertificateInfo>) -> Option<Vec<String>>;
+
+    /// Called when an app needs more new App IDs than the account has left. Returns the ids
+    /// (`appIdId`) of the App IDs to delete before trying again, or `None` to cancel.
+    fn app_ids_to_delete(&self, shortfall: AppIdShortfall) -> Option<Vec<String>>;
+}
+
+tokio::task_local! {
+    /// Token of the operation whose step the task is running, see [`PromptAbort::current`]
+    static OPERATION_TOKEN: CancellationToken;
+}
+
+/// Lets a prompt that is waiting for an answer give up when the operation it was asked for is
+/// cancelled.
+#[derive(Clone, Default)]
+pub struct PromptAbort(Option<CancellationToken>);
+
+impl PromptAbort {
+    /// For a prompt asked now, from within a step of an operation (see [`Operation::run`]).
+    /// Prompts asked outside of an operation, e.g. while logging in, are never aborted.
+    pub fn current() -> Self {
+        PromptAbort(OPERATION_TOKEN.try_with(|token| token.clone()).ok())
+    }
+
+    pub fn is_aborted(&self) -> bool {
+        self.0.as_ref().is_some_and(|token| token.is_cancelled())
+    }
+
+    /// Waits up to `timeout` for an answer on `rx`, returning `None` if there is none or the
+    /// prompt was aborted in the meantime.
+    pub fn wait<T>(&self, rx: &std::sync::mpsc::Receiver<T>, timeout: Duration) -> Option<T> {
+        let deadline = Instant::now() + timeout;
+        while !self.is_aborted() {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return None;
+            }
+            match rx.recv_timeout(remaining.min(Duration::from_millis(200))) {
+                Ok(answer) => return Some(answer),
+                Err(std::sync::mpsc::RecvTimeoutError::Timeout) => continue,
+                Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => return None,
+            }
+        }
+        None
+    }
+
+    /// Runs `fut` with the prompts it asks tied to the operation with cancellation `token`.
+    pub(crate) async fn scope<F: Future>(token: CancellationToken, fut: F) -> F::Output {
+        OPERATION_TOKEN.scope(token, fut).await
+    }
+
+    /// Runs `f` with the prompts it asks tied to the same operation as `self`, for prompting
+    /// from another thread.
+    pub(crate) fn sync_scope<T>(self, f: impl FnOnce() -> T) -> T {
+        match self.0 {
+            Some(token) => OPERATION_TOKEN.sync_scope(token, f),
+            None => f(),
+        }
+    }
+}
+
+/// UI-agnostic entry point to iloader's flows.
+///
+/// Holds the logged in account and the selected device. The Tauri commands and `iloader-cli`
+/// are thin adapters over it, and the flows are implemented next to the code they use
+/// (`account.rs`, `sideload.rs`, `pairing.rs`).
+pub struct IloaderCore {
+    pub(crate) sideloader: SideloaderMutex,
+    pub(crate) device: DeviceInfoMutex,
+    pub(crate) data_dir: PathBuf,
+    pub(crate) temp_dir: PathBuf,
+    pub(crate) operations: OperationRegistry,
+    /// Serializes updates to the download cache index
+    pub(crate) cache_lock: Mutex<()>,
+    pub(crate) jobs: Mutex<JobQueue>,
+    /// Serializes updates to the operation history
+    pub(crate) history_lock: Mutex<()>,
+    /// Serializes updates to the installed apps tracked for expiry
+    pub(crate) installed_apps_lock: Mutex<()>,
+    http_config: Mutex<HttpConfig>,
+    pub(crate) refresh_config: Mutex<RefreshConfig>,
+    /// Held while checking for apps to refresh
+    pub(crate) refresh_lock: tokio::sync::Mutex<()>,
+    /// Port network devices are probed on, only changed by tests
+    pub(crate) lockdown_port: u16,
+}
+
+impl IloaderCore {
+    /// `data_dir` is used for persistent state and the download cache, `temp_dir` for staging
+    /// installs.
+    pub fn new(data_dir: PathBuf, temp_dir: PathBuf) -> Self {
+        IloaderCore {
+            sideloader: SideloaderMutex::new(None),
+            device: DeviceInfoMutex::new(None),
+            data_dir,
+            temp_dir,
+            operations: OperationRegistry::default(),
+            cache_lock: Mutex::new(()),
+            jobs: Mutex::new(JobQueue::default()),
+            history_lock: Mutex::new(()),
+            installed_apps_lock: Mutex::new(()),
+            http_config: Mutex::new(HttpConfig::default()),
+            refresh_config: Mutex::new(RefreshConfig::default()),
+            refresh_lock: tokio::sync::Mutex::new(()),
+            lockdown_port: LOCKDOWN_PORT,
+        }
+    }
+
+    /// Starts an operation named `name`, e.g. `sideload`, under a new unique id. It can be
+    /// cancelled with [`IloaderCore::cancel_operation`] and is recorded in the history.
+    pub fn operation<'a>(&'a self, name: &str, sink: &'a dyn ProgressSink) -> Operation<'a> {
+        self.operation_with_id(uuid::Uuid::new_v4().to_string(), name, sink)
+            .expect("New operation ids are unique")
+    }
+
+    /// Like [`IloaderCore::operation`], under an id chosen by the caller, e.g. so a frontend can
+    /// listen for its progress before it starts. Fails if an operation with the id is running.
+    pub fn operation_with_id<'a>(
+        &'a self,
+        id: String,
+        name: &str,
+        sink: &'a dyn ProgressSink,
+    ) -> Result<Operation<'a>, String> {
+        let mut op = Operation::registered(id, name, sink, &self.operations)?;
+        op.record_history(self);
+        Ok(op)
+    }
+
+    /// Cancels the running operation `id`, also aborting the prompts it is waiting on.
+    pub fn cancel_operation(&self, id: &str) -> Result<(), String> {
+        let token = self
+            .operations
+            .lock()
+            .unwrap()
+            .get(id)
+            .cloned()
+            .ok_or_else(|| format!("Operation {} is not running", id))?;
+        token.cancel();
+        Ok(())
+    }
+
+    pub fn set_http_config(&self, config: HttpConfig) {
+        *self.http_config.lock().unwrap() = config;
+    }
+
+    /// A client for downloads using the current [`HttpConfig`].
+    pub(crate) fn http_client(&self) -> Result<reqwest::Client, String> {
+        self.http_config.lock().unwrap().client()
+    }
+
+    pub fn select_device(&self, device: Option<DeviceInfo>) {
+        *self.device.lock().unwrap() = device;
+    }
+
+    pub fn selected_device(&self) -> Result<DeviceInfo, String> {
+        selected_device(&self.device)
+    }
+
+    /// The connected device with `udid`, or the selected device when `udid` is `None`. Of the
+    /// saved network devices, only the one with `udid` is probed.
+    ///
+    /// Operations resolve their device once up front, so changing the selection while they run
+    /// doesn't change their target.
+    pub async fn device(&self, udid: Option<&str>) -> Result<DeviceInfo, String> {
+        let Some(udid) = udid else {
+            return self.selected_device();
+        };
+        let usb = usbmuxd_devices().await;
+        if let Some(device) = usb.iter().flatten().find(|d| d.uuid == udid) {
+            return Ok(device.clone());
+        }
+        if let Some(device) = self.network_device(udid).await? {
+            return Ok(device);
+        }
+        usb?;
+        Err(format!("Device {} is not connected", udid))
+    }
+
+    /// Clears the selection if `device` is the selected device, returning whether it was.
+    pub fn forget_device(&self, device: &DeviceInfo) -> bool {
+        let mut selected = self.device.lock().unwrap();
+        if selected.as_ref().is_some_and(|d| d.id == device.id) {
+            *selected = None;
+            return true;
+        }
+        false
+    }
+
+    pub(crate) async fn lock_sideloader(&self) -> Result<SideloaderGuard, String> {
+        SideloaderGuard::lock(&self.sideloader).await
+    }
+
+    /// The prompter the logged in account was logged in with.
+    pub(crate) fn prompter(&self) -> Result<Arc<dyn Prompter>, String> {
+        self.sideloader
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|account| account.prompter.clone())
+            .ok_or_else(|| "Not logged in".to_string())
+    }
+}
@@ -0,0 +1,758 @@
+use std::{
+    fs::File,
+    io::{Cursor, Read, Seek, Write},
+    path::Path,
+};
+
+use apple_codesign::MachFile;
+use base64::Engine;
+use flate2::read::DeflateDecoder;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use zip::{CompressionMethod, ZipArchive, ZipWriter, write::SimpleFileOptions};
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// What an IPA contains, read without signing or installing it.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct IpaInfo {
+    pub bundle_id: String,
+    pub name: String,
+    /// `CFBundleShortVersionString`
+    pub version: Option<String>,
+    /// `CFBundleVersion`
+    pub build: Option<String>,
+    pub minimum_os_version: Option<String>,
+    pub extensions: Vec<IpaExtension>,
+    /// Entitlements the main executable was signed with
+    pub entitlements: plist::Dictionary,
+    /// Base64-encoded PNG
+    pub icon: Option<String>,
+    /// One for the app and one for each extension
+    pub app_ids_required: usize,
+}
+
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct IpaExtension {
+    pub bundle_id: String,
+    pub name: String,
+}
+
+/// Changes made to an app before signing it, e.g. to install a second copy next to the first.
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AppOverrides {
+    /// Replaces the app's bundle ID. Extension bundle IDs keep their suffix under the new one.
+    pub bundle_id: Option<String>,
+    /// Replaces the name shown on the home screen
+    pub name: Option<String>,
+    /// Replaces the version string of the app and its extensions
+    pub version: Option<String>,
+}
+
+impl AppOverrides {
+    pub fn is_empty(&self) -> bool {
+        self.bundle_id.is_none() && self.name.is_none() && self.version.is_none()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(bundle_id) = &self.bundle_id {
+            let valid = !bundle_id.is_empty()
+                && !bundle_id.starts_with('.')
+                && !bundle_id.ends_with('.')
+                && bundle_id
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-');
+            if !valid {
+                return Err(format!("Invalid bundle ID: {}", bundle_id));
+            }
+        }
+        if self
+            .name
+            .as_ref()
+            .is_some_and(|name| name.trim().is_empty())
+        {
+            return Err("The app name can't be empty".to_string());
+        }
+        if self
+            .version
+            .as_ref()
+            .is_some_and(|version| version.trim().is_empty())
+        {
+            return Err("The version can't be empty".to_string());
+        }
+        Ok(())
+    }
+
+    /// Applies the overrides to the `Info.plist` of the main app, or of one of its extensions
+    /// when `main_bundle_id` is the app's original bundle ID.
+    fn apply(&self, info: &mut plist::Dictionary, main_bundle_id: Option<&str>) {
+        if let Some(bundle_id) = &self.bundle_id {
+            let renamed = match main_bundle_id {
+                Some(main_bundle_id) => string_key(info, "CFBundleIdentifier")
+                    .and_then(|id| renamed_extension_id(&id, main_bundle_id, bundle_id)),
+                None => Some(bundle_id.clone()),
+            };
+            if let Some(renamed) = renamed {
+                info.insert("CFBundleIdentifier".to_string(), renamed.into());
+            }
+        }
+        if let Some(name) = &self.name
+            && main_bundle_id.is_none()
+        {
+            info.insert("CFBundleDisplayName".to_string(), name.clone().into());
+        }
+        if let Some(version) = &self.version {
+            info.insert(
+                "CFBundleShortVersionString".to_string(),
+                version.clone().into(),
+            );
+        }
+    }
+}
+
+impl IpaInfo {
+    /// What the app will look like once `overrides` are applied with [`rewrite_ipa`].
+    pub fn with_overrides(mut self, overrides: &AppOverrides) -> Self {
+        if let Some(bundle_id) = &overrides.bundle_id {
+            for extension in &mut self.extensions {
+                if let Some(renamed) =
+                    renamed_extension_id(&extension.bundle_id, &self.bundle_id, bundle_id)
+                {
+                    extension.bundle_id = renamed;
+                }
+            }
+            self.bundle_id = bundle_id.clone();
+        }
+        if let Some(name) = &overrides.name {
+            self.name = name.clone();
+        }
+        if let Some(version) = &overrides.version {
+            self.version = Some(version.clone());
+        }
+        self
+    }
+}
+
+/// isideload only accepts extensions whose bundle ID starts with the app's, so an extension
+/// keeps whatever follows the old bundle ID.
+fn renamed_extension_id(id: &str, old_main: &str, new_main: &str) -> Option<String> {
+    id.strip_prefix(old_main)
+        .filter(|suffix| !suffix.is_empty())
+        .map(|suffix| format!("{}{}", new_main, suffix))
+}
+
+/// Checks that `path` is a complete zip containing an app bundle at `Payload/<name>.app`.
+pub fn check_ipa(path: &Path) -> Result<(), String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open IPA: {}", e))?;
+    check_ipa_reader(file)
+}
+
+fn check_ipa_reader(reader: impl Read + Seek) -> Result<(), String> {
+    // Fails if the central directory at the end of the archive is missing, e.g. when truncated
+    let mut archive = ZipArchive::new(reader).map_err(|e| format!("Invalid zip archive: {}", e))?;
+
+    let info_plist = archive
+        .file_names()
+        .find(|name| is_app_info_plist(name))
+        .map(|name| name.to_string())
+        .ok_or_else(|| "Payload/*.app/Info.plist not found".to_string())?;
+
+    // Reading the entry checks its CRC against the central directory
+    let contents = read_entry(&mut archive, &info_plist)?;
+    plist::Value::from_reader(Cursor::new(contents))
+        .map_err(|e| format!("Invalid {}: {}", info_plist, e))?;
+
+    Ok(())
+}
+
+/// Reads the app's metadata, extensions, entitlements and icon from the IPA at `path`.
+pub fn ipa_info(path: &Path) -> Result<IpaInfo, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open IPA: {}", e))?;
+    let mut archive = ZipArchive::new(file).map_err(|e| format!("Invalid zip archive: {}", e))?;
+
+    let info_plist = archive
+        .file_names()
+        .find(|name| is_app_info_plist(name))
+        .map(|name| name.to_string())
+        .ok_or_else(|| "Payload/*.app/Info.plist not found".to_string())?;
+    let app_dir = info_plist.trim_end_matches("Info.plist").to_string();
+    let info = read_plist(&mut archive, &info_plist)?;
+
+    let mut extension_plists: Vec<String> = archive
+        .file_names()
+        .filter(|name| is_extension_info_plist(&app_dir, name))
+        .map(|name| name.to_string())
+        .collect();
+    extension_plists.sort();
+    let mut extensions = Vec::new();
+    for name in &extension_plists {
+        let info = read_plist(&mut archive, name)?;
+        extensions.push(IpaExtension {
+            bundle_id: string_key(&info, "CFBundleIdentifier").unwrap_or_default(),
+            name: bundle_name(&info).unwrap_or_default(),
+        });
+    }
+
+    let entitlements = match string_key(&info, "CFBundleExecutable") {
+        Some(executable) => read_entitlements(&mut archive, &format!("{}{}", app_dir, executable))
+            .unwrap_or_else(|e| {
+                warn!("Failed to read entitlements: {}", e);
+                plist::Dictionary::new()
+            }),
+        None => plist::Dictionary::new(),
+    };
+
+    let icon = read_icon(&mut archive, &app_dir, &info).unwrap_or_else(|e| {
+        warn!("Failed to read app icon: {}", e);
+        None
+    });
+
+    Ok(IpaInfo {
+        bundle_id: string_key(&info, "CFBundleIdentifier")
+            .ok_or_else(|| "Info.plist has no CFBundleIdentifier".to_string())?,
+        name: bundle_name(&info).unwrap_or_default(),
+        version: string_key(&info, "CFBundleShortVersionString"),
+        build: string_key(&info, "CFBundleVersion"),
+        minimum_os_version: string_key(&info, "MinimumOSVersion"),
+        app_ids_required: 1 + extensions.len(),
+        extensions,
+        entitlements,
+        icon: icon.map(|png| base64::engine::general_purpose::STANDARD.encode(png)),
+    })
+}
+
+/// Copies the IPA at `src` to `dest`, applying `overrides` to the `Info.plist` of the app and
+/// its extensions. Other entries are copied without recompressing them.
+pub fn rewrite_ipa(src: &Path, dest: &Path, overrides: &AppOverrides) -> Result<(), String> {
+    let file = File::open(src).map_err(|e| format!("Failed to open IPA: {}", e))?;
+    let mut archive = ZipArchive::new(file).map_err(|e| format!("Invalid zip archive: {}", e))?;
+
+    let info_plist = archive
+        .file_names()
+        .find(|name| is_app_info_plist(name))
+        .map(|name| name.to_string())
+        .ok_or_else(|| "Payload/*.app/Info.plist not found".to_string())?;
+    let app_dir = info_plist.trim_end_matches("Info.plist").to_string();
+    let main_bundle_id = string_key(
+        &read_plist(&mut archive, &info_plist)?,
+        "CFBundleIdentifier",
+    )
+    .ok_or_else(|| "Info.plist has no CFBundleIdentifier".to_string())?;
+
+    let out = File::create(dest).map_err(|e| format!("Failed to create IPA: {}", e))?;
+    let mut writer = ZipWriter::new(out);
+    for i in 0..archive.len() {
+        let name = archive
+            .by_index_raw(i)
+            .map_err(|e| format!("Failed to read archive: {}", e))?
+            .name()
+            .to_string();
+        let is_extension = is_extension_info_plist(&app_dir, &name);
+        if name != info_plist && !is_extension {
+            let entry = archive
+                .by_index_raw(i)
+                .map_err(|e| format!("Failed to read archive: {}", e))?;
+            writer
+                .raw_copy_file(entry)
+                .map_err(|e| format!("Failed to write IPA: {}", e))?;
+            continue;
+        }
+
+        let mut info = read_plist(&mut archive, &name)?;
+        overrides.apply(&mut info, is_extension.then_some(main_bundle_id.as_str()));
+        let mut contents = Vec::new();
+        plist::to_writer_binary(&mut contents, &info)
+            .map_err(|e| format!("Failed to serialize {}: {}", name, e))?;
+
+        let mut options =
+            SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        if let Some(mode) = archive.by_index_raw(i).ok().and_then(|e| e.unix_mode()) {
+            options = options.unix_permissions(mode);
+        }
+        writer
+            .start_file(&name, options)
+            .and_then(|_| writer.write_all(&contents).map_err(Into::into))
+            .map_err(|e| format!("Failed to write IPA: {}", e))?;
+    }
+    writer
+        .finish()
+        .map_err(|e| format!("Failed to write IPA: {}", e))?;
+    Ok(())
+}
+
+fn read_entry<R: Read + Seek>(archive: &mut ZipArchive<R>, name: &str) -> Result<Vec<u8>, String> {
+    let mut entry = archive
+        .by_name(name)
+        .map_err(|e| format!("Failed to read {}: {}", name, e))?;
+    let mut contents = Vec::new();
+    entry
+        .read_to_end(&mut contents)
+        .map_err(|e| format!("Failed to read {}: {}", name, e))?;
+    Ok(contents)
+}
+
+fn read_plist<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    name: &str,
+) -> Result<plist::Dictionary, String> {
+    plist::from_bytes(&read_entry(archive, name)?).map_err(|e| format!("Invalid {}: {}", name, e))
+}
+
+fn string_key(info: &plist::Dictionary, key: &str) -> Option<String> {
+    info.get(key)
+        .and_then(|v| v.as_string())
+        .map(|s| s.to_string())
+}
+
+fn bundle_name(info: &plist::Dictionary) -> Option<String> {
+    string_key(info, "CFBundleDisplayName").or_else(|| string_key(info, "CFBundleName"))
+}
+
+/// The entitlements embedded in the code signature of the executable at `name`. For fat
+/// binaries the first architecture is used, they are signed with the same entitlements.
+fn read_entitlements(
+    archive: &mut ZipArchive<File>,
+    name: &str,
+) -> Result<plist::Dictionary, String> {
+    let data = read_entry(archive, name)?;
+    let mach = MachFile::parse(&data).map_err(|e| format!("Invalid executable: {}", e))?;
+    let Some(macho) = mach.iter_macho().next() else {
+        return Ok(plist::Dictionary::new());
+    };
+    let signature = macho
+        .code_signature()
+        .map_err(|e| format!("Invalid code signature: {}", e))?;
+    let entitlements = match signature {
+        Some(signature) => signature
+            .entitlements()
+            .map_err(|e| format!("Invalid code signature: {}", e))?,
+        None => None,
+    };
+    match entitlements {
+        Some(entitlements) => plist::from_bytes(entitlements.as_str().as_bytes())
+            .map_err(|e| format!("Invalid entitlements: {}", e)),
+        None => Ok(plist::Dictionary::new()),
+    }
+}
+
+/// The largest PNG among the app's primary icon files, converted to a standard PNG.
+fn read_icon(
+    archive: &mut ZipArchive<File>,
+    app_dir: &str,
+    info: &plist::Dictionary,
+) -> Result<Option<Vec<u8>>, String> {
+    let mut icon_names: Vec<String> = Vec::new();
+    for icons_key in ["CFBundleIcons", "CFBundleIcons~ipad"] {
+        if let Some(files) = info
+            .get(icons_key)
+            .and_then(|v| v.as_dictionary())
+            .and_then(|icons| icons.get("CFBundlePrimaryIcon"))
+            .and_then(|v| v.as_dictionary())
+            .and_then(|primary| primary.get("CFBundleIconFiles"))
+            .and_then(|v| v.as_array())
+        {
+            icon_names.extend(files.iter().filter_map(|f| f.as_string()).map(String::from));
+        }
+    }
+    if let Some(files) = info.get("CFBundleIconFiles").and_then(|v| v.as_array()) {
+        icon_names.extend(files.iter().filter_map(|f| f.as_string()).map(String::from));
+    }
+    icon_names.extend(string_key(info, "CFBundleIconFile"));
+
+    let mut best: Option<(u64, String)> = None;
+    for i in 0..archive.len() {
+        let entry = archive
+            .by_index(i)
+            .map_err(|e| format!("Failed to read archive: {}", e))?;
+        let Some(file_name) = entry.name().strip_prefix(app_dir) else {
+            continue;
+        };
+        let is_icon = file_name.ends_with(".png")
+            && !file_name.contains('/')
+            && icon_names
+                .iter()
+                .any(|icon| file_name.starts_with(icon.trim_end_matches(".png")));
+        if is_icon && best.as_ref().is_none_or(|(size, _)| entry.size() > *size) {
+            best = Some((entry.size(), entry.name().to_string()));
+        }
+    }
+
+    match best {
+        Some((_, name)) => Ok(Some(normalize_png(&read_entry(archive, &name)?)?)),
+        None => Ok(None),
+    }
+}
+
+/// Xcode stores app icons as "CgBI" PNGs, which other decoders can't read: the IDAT stream
+/// lacks its zlib header and the pixels are premultiplied BGRA. Converts those to a regular
+/// RGBA PNG and returns anything else unchanged.
+fn normalize_png(data: &[u8]) -> Result<Vec<u8>, String> {
+    if !data.starts_with(PNG_SIGNATURE) {
+        return Err("Not a PNG".to_string());
+    }
+
+    let mut is_cgbi = false;
+    let mut header = None;
+    let mut idat = Vec::new();
+    let mut rest = &data[PNG_SIGNATURE.len()..];
+    while rest.len() >= 12 {
+        let length = u32::from_be_bytes(rest[0..4].try_into().unwrap()) as usize;
+        if rest.len() < 12 + length {
+            return Err("Truncated PNG".to_string());
+        }
+        let chunk = &rest[8..8 + length];
+        match &rest[4..8] {
+            b"CgBI" => is_cgbi = true,
+            b"IHDR" if length >= 13 => header = Some(chunk),
+            b"IDAT" => idat.extend_from_slice(chunk),
+            b"IEND" => break,
+            _ => {}
+        }
+        rest = &rest[12 + length..];
+    }
+    if !is_cgbi {
+        return Ok(data.to_vec());
+    }
+
+    let header = header.ok_or_else(|| "PNG has no header".to_string())?;
+    let width = u32::from_be_bytes(header[0..4].try_into().unwrap());
+    let height = u32::from_be_bytes(header[4..8].try_into().unwrap());
+    // Bit depth 8, color type RGBA, not interlaced
+    if header[8] != 8 || header[9] != 6 || header[12] != 0 {
+        return Err("Unsupported CgBI PNG format".to_string());
+    }
+
+    let mut filtered = Vec::new();
+    DeflateDecoder::new(&idat[..])
+        .read_to_end(&mut filtered)
+        .map_err(|e| format!("Failed to decompress PNG: {}", e))?;
+    let stride = width as usize * 4;
+    if filtered.len() < (stride + 1) * height as usize {
+        return Err("Truncated PNG".to_string());
+    }
+
+    let mut pixels = vec![0u8; stride * height as usize];
+    for y in 0..height as usize {
+        let filter = filtered[y * (stride + 1)];
+        let line = &filtered[y * (stride + 1) + 1..(y + 1) * (stride + 1)];
+        let (previous, current) = pixels.split_at_mut(y * stride);
+        let above = if y == 0 {
+            None
+        } else {
+            Some(&previous[(y - 1) * stride..])
+        };
+        let current = &mut current[..stride];
+        for x in 0..stride {
+            let a = if x >= 4 { current[x - 4] } else { 0 };
+            let b = above.map_or(0, |above| above[x]);
+            let c = match above {
+                Some(above) if x >= 4 => above[x - 4],
+                _ => 0,
+            };
+            let predicted = match filter {
+                0 => 0,
+                1 => a,
+                2 => b,
+                3 => ((a as u16 + b as u16) / 2) as u8,
+                4 => paeth(a, b, c),
+                _ => return Err(format!("Invalid PNG filter {}", filter)),
+            };
+            current[x] = line[x].wrapping_add(predicted);
+        }
+    }
+
+    for pixel in pixels.chunks_exact_mut(4) {
+        pixel.swap(0, 2);
+        let alpha = pixel[3] as u16;
+        if alpha > 0 && alpha < 255 {
+            for channel in &mut pixel[..3] {
+                *channel = ((*channel as u16 * 255 + alpha / 2) / alpha).min(255) as u8;
+            }
+        }
+    }
+
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder
+        .write_header()
+        .map_err(|e| format!("Failed to encode PNG: {}", e))?;
+    writer
+        .write_image_data(&pixels)
+        .map_err(|e| format!("Failed to encode PNG: {}", e))?;
+    writer
+        .finish()
+        .map_err(|e| format!("Failed to encode PNG: {}", e))?;
+    Ok(png)
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let pa = (p - a as i16).abs();
+    let pb = (p - b as i16).abs();
+    let pc = (p - c as i16).abs();
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+fn is_extension_info_plist(app_dir: &str, name: &str) -> bool {
+    let Some(rest) = name.strip_prefix(app_dir) else {
+        return false;
+    };
+    let parts: Vec<&str> = rest.split('/').collect();
+    matches!(parts[..], ["PlugIns", appex, "Info.plist"] if appex.ends_with(".appex"))
+}
+
+fn is_app_info_plist(name: &str) -> bool {
+    let parts: Vec<&str> = name.split('/').collect();
+    matches!(parts[..], ["Payload", app, "Info.plist"] if app.ends_with(".app"))
+}
+
+#[tauri::command]
+pub async fn inspect_ipa(path: String) -> Result<IpaInfo, String> {
+    tokio::task::spawn_blocking(move || ipa_info(Path::new(&path)))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use flate2::{Compression, write::DeflateEncoder};
+
+    use super::*;
+
+    fn zip(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, contents) in entries {
+            writer
+                .start_file(*name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(contents).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn info_plist(bundle_id: &str) -> Vec<u8> {
+        let mut info = plist::Dictionary::new();
+        info.insert("CFBundleIdentifier".to_string(), bundle_id.into());
+        info.insert("CFBundleName".to_string(), "Test".into());
+        let mut contents = Vec::new();
+        plist::to_writer_xml(&mut contents, &info).unwrap();
+        contents
+    }
+
+    fn png_chunk(kind: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+        chunk.extend_from_slice(kind);
+        chunk.extend_from_slice(data);
+        // normalize_png doesn't check CRCs
+        chunk.extend_from_slice(&[0; 4]);
+        chunk
+    }
+
+    #[test]
+    fn check_ipa_accepts_app_in_payload() {
+        let ipa = zip(&[
+            (
+                "Payload/Test.app/Info.plist",
+                &info_plist("com.example.test"),
+            ),
+            ("Payload/Test.app/Test", b"binary"),
+        ]);
+        assert_eq!(check_ipa_reader(Cursor::new(ipa)), Ok(()));
+    }
+
+    #[test]
+    fn check_ipa_rejects_malformed_ipas() {
+        let info = info_plist("com.example.test");
+        for entries in [
+            // Not under Payload
+            vec![("Test.app/Info.plist", &info[..])],
+            // Not in an app bundle
+            vec![("Payload/Info.plist", &info[..])],
+            // Only a nested bundle
+            vec![(
+                "Payload/Test.app/Frameworks/A.framework/Info.plist",
+                &info[..],
+            )],
+            vec![("Payload/Test.app/Info.plist", b"not a plist")],
+        ] {
+            let ipa = zip(&entries);
+            assert!(check_ipa_reader(Cursor::new(ipa)).is_err());
+        }
+
+        let mut truncated = zip(&[("Payload/Test.app/Info.plist", &info)]);
+        truncated.truncate(truncated.len() - 10);
+        assert!(check_ipa_reader(Cursor::new(truncated)).is_err());
+        assert!(check_ipa_reader(Cursor::new(b"not a zip".to_vec())).is_err());
+    }
+
+    #[test]
+    fn extension_info_plists_are_found_in_plugins() {
+        let app_dir = "Payload/Test.app/";
+        assert!(is_extension_info_plist(
+            app_dir,
+            "Payload/Test.app/PlugIns/Widget.appex/Info.plist"
+        ));
+        assert!(!is_extension_info_plist(
+            app_dir,
+            "Payload/Test.app/PlugIns/Widget.appex/Frameworks/A.framework/Info.plist"
+        ));
+        assert!(!is_extension_info_plist(
+            app_dir,
+            "Payload/Other.app/PlugIns/Widget.appex/Info.plist"
+        ));
+        assert!(!is_extension_info_plist(
+            app_dir,
+            "Payload/Test.app/Frameworks/A.framework/Info.plist"
+        ));
+    }
+
+    #[test]
+    fn rewrite_ipa_applies_overrides_to_app_and_extensions() {
+        let ipa = zip(&[
+            (
+                "Payload/Test.app/Info.plist",
+                &info_plist("com.example.test"),
+            ),
+            ("Payload/Test.app/Test", b"binary"),
+            (
+                "Payload/Test.app/PlugIns/Widget.appex/Info.plist",
+                &info_plist("com.example.test.widget"),
+            ),
+            (
+                "Payload/Test.app/PlugIns/Share.appex/Info.plist",
+                &info_plist("com.other.share"),
+            ),
+        ]);
+        let dir = std::env::temp_dir().join(format!("iloader-test-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (src, dest) = (dir.join("src.ipa"), dir.join("dest.ipa"));
+        std::fs::write(&src, ipa).unwrap();
+        let overrides = AppOverrides {
+            bundle_id: Some("com.example.beta".to_string()),
+            name: Some("Beta".to_string()),
+            version: Some("2.0".to_string()),
+        };
+        rewrite_ipa(&src, &dest, &overrides).unwrap();
+
+        let mut archive = ZipArchive::new(File::open(&dest).unwrap()).unwrap();
+        let app = read_plist(&mut archive, "Payload/Test.app/Info.plist").unwrap();
+        assert_eq!(
+            string_key(&app, "CFBundleIdentifier").as_deref(),
+            Some("com.example.beta")
+        );
+        assert_eq!(
+            string_key(&app, "CFBundleDisplayName").as_deref(),
+            Some("Beta")
+        );
+        assert_eq!(
+            string_key(&app, "CFBundleShortVersionString").as_deref(),
+            Some("2.0")
+        );
+
+        let widget = read_plist(
+            &mut archive,
+            "Payload/Test.app/PlugIns/Widget.appex/Info.plist",
+        )
+        .unwrap();
+        assert_eq!(
+            string_key(&widget, "CFBundleIdentifier").as_deref(),
+            Some("com.example.beta.widget")
+        );
+        assert_eq!(string_key(&widget, "CFBundleDisplayName"), None);
+        assert_eq!(
+            string_key(&widget, "CFBundleShortVersionString").as_deref(),
+            Some("2.0")
+        );
+
+        // Extensions outside the app's bundle ID are left for isideload to reject
+        let share = read_plist(
+            &mut archive,
+            "Payload/Test.app/PlugIns/Share.appex/Info.plist",
+        )
+        .unwrap();
+        assert_eq!(
+            string_key(&share, "CFBundleIdentifier").as_deref(),
+            Some("com.other.share")
+        );
+        assert_eq!(
+            read_entry(&mut archive, "Payload/Test.app/Test").unwrap(),
+            b"binary"
+        );
+
+        // The preview shown before installing matches the rewritten IPA
+        let preview = ipa_info(&src).unwrap().with_overrides(&overrides);
+        let rewritten = ipa_info(&dest).unwrap();
+        assert_eq!(preview.bundle_id, rewritten.bundle_id);
+        assert_eq!(preview.name, rewritten.name);
+        assert_eq!(preview.version, rewritten.version);
+        let extension_ids = |info: &IpaInfo| -> Vec<String> {
+            info.extensions
+                .iter()
+                .map(|e| e.bundle_id.clone())
+                .collect()
+        };
+        assert_eq!(extension_ids(&preview), extension_ids(&rewritten));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn normalize_png_keeps_standard_pngs() {
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, 1, 1);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&[1, 2, 3, 4]).unwrap();
+        writer.finish().unwrap();
+
+        assert_eq!(normalize_png(&png), Ok(png));
+        assert!(normalize_png(b"GIF89a").is_err());
+    }
+
+    #[test]
+    fn normalize_png_converts_cgbi() {
+        // Two rows of two premultiplied BGRA pixels: opaque red and half transparent red. The
+        // second row uses the "up" filter, so it repeats the first.
+        let mut filtered = vec![0, 0, 0, 255, 255, 0, 0, 64, 128];
+        filtered.extend_from_slice(&[2, 0, 0, 0, 0, 0, 0, 0, 0]);
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&filtered).unwrap();
+        let idat = encoder.finish().unwrap();
+
+        let mut header = Vec::new();
+        header.extend_from_slice(&2u32.to_be_bytes());
+        header.extend_from_slice(&2u32.to_be_bytes());
+        header.extend_from_slice(&[8, 6, 0, 0, 0]);
+        let mut cgbi = PNG_SIGNATURE.to_vec();
+        cgbi.extend(png_chunk(b"CgBI", &[0x50, 0x00, 0x20, 0x06]));
+        cgbi.extend(png_chunk(b"IHDR", &header));
+        cgbi.extend(png_chunk(b"IDAT", &idat));
+        cgbi.extend(png_chunk(b"IEND", &[]));
+
+        let png = normalize_png(&cgbi).unwrap();
+        let mut reader = png::Decoder::new(&png[..]).read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!((info.width, info.height), (2, 2));
+        assert_eq!(info.color_type, png::ColorType::Rgba);
+        let row = [255, 0, 0, 255, 128, 0, 0, 128];
+        assert_eq!(&pixels[..8], &row);
+        assert_eq!(&pixels[8..16], &row);
+
+        let mut truncated = cgbi.clone();
+        truncated.truncate(cgbi.len() - 16);
+        assert!(normalize_png(&truncated).is_err());
+    }
+}
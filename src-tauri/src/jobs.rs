@@ -0,0 +1,559 @@
+use std::{
+    collections::HashSet,
+    fs::{File, OpenOptions, TryLockError},
+    path::PathBuf,
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+use tracing::{info, warn};
+
+use crate::{
+    iloader_core::IloaderCore,
+    ipa::AppOverrides,
+    operation::{CANCELLED, Operation, ProgressSink},
+};
+
+const JOBS_FILE: &str = "jobs.json";
+/// Locked while jobs are read and written, since the app and the CLI can share the data directory
+const JOBS_LOCK_FILE: &str = "jobs.lock";
+/// Holds a lock file for each job a process is running, see [`JobOwner`]
+const JOB_OWNERS_DIR: &str = "job_owners";
+/// How often a worker checks whether its device is connected and an account is logged in
+const READY_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// What a queued job does. Each kind runs the flow of the operation with the same name.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum JobKind {
+    Sideload {
+        app_path: String,
+        #[serde(default)]
+        overrides: AppOverrides,
+    },
+    SideloadUrl {
+        url: String,
+        sha256: Option<String>,
+    },
+    InstallSidestore {
+        source: String,
+        nightly: bool,
+        sha256: Option<String>,
+    },
+    InstallFeedApp {
+        feed_url: String,
+        bundle_id: String,
+        version: Option<String>,
+    },
+}
+
+impl JobKind {
+    /// Name of the operation running the job, see [`IloaderCore::operation`].
+    pub fn operation_name(&self) -> &'static str {
+        match self {
+            JobKind::Sideload { .. } => "sideload",
+            JobKind::SideloadUrl { .. } => "sideload_url",
+            JobKind::InstallSidestore { .. } => "install_sidestore",
+            JobKind::InstallFeedApp { .. } => "install_feed_app",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum JobState {
+    Pending,
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Job {
+    pub id: String,
+    pub kind: JobKind,
+    pub udid: String,
+    pub state: JobState,
+    /// How many times the job was started
+    pub attempts: u32,
+    /// Why the last attempt failed
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Job {
+    /// Id of the [`Operation`] running the job, see [`IloaderCore::cancel_operation`].
+    pub fn operation_id(&self) -> String {
+        format!("job_{}", self.id)
+    }
+}
+
+/// The devices with a worker running their jobs in this process. The jobs themselves are
+/// reloaded from the data directory every time, see [`IloaderCore::with_jobs`].
+#[derive(Default)]
+pub struct JobQueue {
+    workers: HashSet<String>,
+}
+
+/// Locks a file for a job while this process runs it, so another process sharing the data
+/// directory can tell a running job from one left running by a process that exited. The OS
+/// releases the lock if the process dies.
+struct JobOwner {
+    _file: File,
+    path: PathBuf,
+}
+
+impl Drop for JobOwner {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Stops counting a worker as running if it exits early, including by panicking or being dropped.
+struct WorkerGuard<'a> {
+    core: &'a IloaderCore,
+    udid: &'a str,
+    released: bool,
+}
+
+impl Drop for WorkerGuard<'_> {
+    fn drop(&mut self) {
+        if self.released {
+            return;
+        }
+        if let Ok(mut queue) = self.core.jobs.lock() {
+            queue.workers.remove(self.udid);
+        }
+    }
+}
+
+impl IloaderCore {
+    pub fn jobs(&self) -> Result<Vec<Job>, String> {
+        let _lock = self.jobs.lock().unwrap();
+        let _file_lock = self.lock_jobs_file()?;
+        self.saved_jobs()
+    }
+
+    /// Queues `kind` to run on the device with `udid`. Start a worker for the device with
+    /// [`IloaderCore::run_jobs`] to run it.
+    pub fn enqueue_job(&self, kind: JobKind, udid: String) -> Result<Job, String> {
+        let now = Utc::now();
+        let job = Job {
+            id: uuid::Uuid::new_v4().to_string(),
+            kind,
+            udid,
+            state: JobState::Pending,
+            attempts: 0,
+            error: None,
+            created_at: now,
+            updated_at: now,
+        };
+        self.with_jobs(|jobs| {
+            jobs.push(job.clone());
+            Ok(())
+        })?;
+        Ok(job)
+    }
+
+    /// Cancels a pending job, or the operation of a running one. A job running in another
+    /// process, e.g. the CLI while the app is open, has to be cancelled there.
+    pub fn cancel_job(&self, id: &str) -> Result<(), String> {
+        let running = self.with_jobs(|jobs| {
+            let job = find_job(jobs, id)?;
+            let operation_id = job.operation_id();
+            match job.state {
+                JobState::Running
+                    if self.operations.lock().unwrap().contains_key(&operation_id) =>
+                {
+                    Ok(Some(operation_id))
+                }
+                JobState::Running if self.job_has_owner(id) => Err(format!(
+                    "Job {} is running in another iloader process, cancel it there",
+                    id
+                )),
+                // Left running by a process that exited
+                JobState::Pending | JobState::Running => {
+                    set_state(job, JobState::Cancelled);
+                    Ok(None)
+                }
+                _ => Err(format!("Job {} has already finished", id)),
+            }
+        })?;
+        match running {
+            // The worker marks the job as cancelled once its operation stops
+            Some(operation_id) => self.cancel_operation(&operation_id),
+            None => Ok(()),
+        }
+    }
+
+    /// Queues a failed or cancelled job again, returning it.
+    pub fn retry_job(&self, id: &str) -> Result<Job, String> {
+        self.with_jobs(|jobs| {
+            let job = find_job(jobs, id)?;
+            if !matches!(job.state, JobState::Failed | JobState::Cancelled) {
+                return Err(format!("Job {} hasn't failed", id));
+            }
+            set_state(job, JobState::Pending);
+            job.error = None;
+            Ok(job.clone())
+        })
+    }
+
+    /// Removes the jobs that succeeded, failed or were cancelled.
+    pub fn clear_finished_jobs(&self) -> Result<(), String> {
+        self.with_jobs(|jobs| {
+            jobs.retain(|job| matches!(job.state, JobState::Pending | JobState::Running));
+            Ok(())
+        })
+    }
+
+    /// Requeues jobs that were running when iloader last exited, returning the UDIDs of the
+    /// devices with pending jobs. Call once at startup before starting any worker.
+    ///
+    /// Jobs still running in another process, e.g. the app while the CLI starts, are left alone.
+    pub fn resume_jobs(&self) -> Result<Vec<String>, String> {
+        self.with_jobs(|jobs| {
+            let mut udids = Vec::new();
+            for job in jobs.iter_mut() {
+                if job.state == JobState::Running && !self.job_has_owner(&job.id) {
+                    info!("Resuming interrupted job {}", job.id);
+                    set_state(job, JobState::Pending);
+                }
+                if job.state == JobState::Pending && !udids.contains(&job.udid) {
+                    udids.push(job.udid.clone());
+                }
+            }
+            Ok(udids)
+        })
+    }
+
+    /// Runs the pending jobs for the device with `udid` one after another until there are none
+    /// left, waiting for the device to be connected and an account to be logged in before each.
+    /// Returns right away if a worker is already running the device's jobs.
+    pub async fn run_jobs(&self, udid: &str, sink: &dyn ProgressSink) -> Result<(), String> {
+        if !self.jobs.lock().unwrap().workers.insert(udid.to_string()) {
+            return Ok(());
+        }
+        let mut guard = WorkerGuard {
+            core: self,
+            udid,
+            released: false,
+        };
+
+        loop {
+            // Checked under the same lock as enqueueing, so a job added after the worker stops
+            // starts a new one
+            let next = self.with_queue(|jobs, workers| {
+                let next = jobs
+                    .iter()
+                    .find(|j| j.udid == udid && j.state == JobState::Pending)
+                    .cloned();
+                if next.is_none() {
+                    workers.remove(udid);
+                }
+                Ok(next)
+            });
+            let job = match next {
+                Ok(Some(job)) => job,
+                Ok(None) => {
+                    guard.released = true;
+                    return Ok(());
+                }
+                Err(e) => return Err(e),
+            };
+
+            if self.logged_in_as().is_none() || self.device(Some(udid)).await.is_err() {
+                tokio::time::sleep(READY_POLL_INTERVAL).await;
+                continue;
+            }
+
+            // Taken before the job is marked as running, so a running job always has an owner
+            let Some(owner) = self.own_job(&job.id)? else {
+                // Another process is starting it, wait until it shows as running there
+                tokio::time::sleep(READY_POLL_INTERVAL).await;
+                continue;
+            };
+            // The job may have been cancelled while waiting, or started by another process
+            let started = self.with_jobs(|jobs| {
+                let job = find_job(jobs, &job.id)?;
+                if job.state != JobState::Pending {
+                    return Ok(false);
+                }
+                set_state(job, JobState::Running);
+                job.attempts += 1;
+                Ok(true)
+            })?;
+            if !started {
+                continue;
+            }
+
+            info!("Running job {}", job.id);
+            let op = self.operation_with_id(job.operation_id(), job.kind.operation_name(), sink);
+            let result = match op {
+                Ok(op) => self.run_job(&op, &job).await,
+                Err(e) => Err(e),
+            };
+
+            self.with_jobs(|jobs| {
+                let job = find_job(jobs, &job.id)?;
+                match result {
+                    Ok(()) => set_state(job, JobState::Succeeded),
+                    Err(e) if e == CANCELLED => set_state(job, JobState::Cancelled),
+                    Err(e) => {
+                        warn!("Job {} failed: {}", job.id, e);
+                        set_state(job, JobState::Failed);
+                        job.error = Some(e);
+                    }
+                }
+                Ok(())
+            })?;
+            drop(owner);
+        }
+    }
+
+    async fn run_job(&self, op: &Operation<'_>, job: &Job) -> Result<(), String> {
+        let udid = Some(job.udid.as_str());
+        match &job.kind {
+            JobKind::Sideload {
+                app_path,
+                overrides,
+            } => self.sideload(op, udid, app_path.clone(), overrides).await,
+            JobKind::SideloadUrl { url, sha256 } => {
+                self.sideload_url(op, udid, url, sha256.as_deref()).await
+            }
+            JobKind::InstallSidestore {
+                source,
+                nightly,
+                sha256,
+            } => {
+                self.install_sidestore(op, udid, source, *nightly, sha256.as_deref())
+                    .await
+            }
+            JobKind::InstallFeedApp {
+                feed_url,
+                bundle_id,
+                version,
+            } => {
+                self.install_feed_app(op, udid, feed_url, bundle_id, version.as_deref())
+                    .await
+            }
+        }
+    }
+
+    /// Runs `f` on the queued jobs and saves them if it succeeds and changed them. The jobs are
+    /// reloaded under a file lock each time, so changes made by another process aren't
+    /// overwritten.
+    fn with_jobs<T>(
+        &self,
+        f: impl FnOnce(&mut Vec<Job>) -> Result<T, String>,
+    ) -> Result<T, String> {
+        self.with_queue(|jobs, _| f(jobs))
+    }
+
+    /// [`IloaderCore::with_jobs`], also passing the devices with a worker in this process.
+    fn with_queue<T>(
+        &self,
+        f: impl FnOnce(&mut Vec<Job>, &mut HashSet<String>) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let mut queue = self.jobs.lock().unwrap();
+        let _file_lock = self.lock_jobs_file()?;
+        let saved = self.saved_jobs()?;
+        let mut jobs = saved.clone();
+        let result = f(&mut jobs, &mut queue.workers)?;
+        if jobs != saved {
+            self.save_jobs(&jobs)?;
+        }
+        Ok(result)
+    }
+
+    /// Waits for other processes to finish with the jobs, then locks them until the returned file
+    /// is dropped.
+    fn lock_jobs_file(&self) -> Result<File, String> {
+        std::fs::create_dir_all(&self.data_dir)
+            .map_err(|e| format!("Failed to create data directory: {}", e))?;
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.data_dir.join(JOBS_LOCK_FILE))
+            .map_err(|e| format!("Failed to open jobs lock: {}", e))?;
+        file.lock()
+            .map_err(|e| format!("Failed to lock jobs: {}", e))?;
+        Ok(file)
+    }
+
+    /// Locks the job with `id` for this process, `None` if another process already has.
+    fn own_job(&self, id: &str) -> Result<Option<JobOwner>, String> {
+        let dir = self.data_dir.join(JOB_OWNERS_DIR);
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create job owners directory: {}", e))?;
+        let path = dir.join(format!("{}.lock", id));
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .map_err(|e| format!("Failed to open job lock: {}", e))?;
+        match file.try_lock() {
+            Ok(()) => Ok(Some(JobOwner { _file: file, path })),
+            Err(TryLockError::WouldBlock) => Ok(None),
+            Err(TryLockError::Error(e)) => Err(format!("Failed to lock job: {}", e)),
+        }
+    }
+
+    /// Whether a process is running the job with `id`, see [`JobOwner`].
+    fn job_has_owner(&self, id: &str) -> bool {
+        let path = self
+            .data_dir
+            .join(JOB_OWNERS_DIR)
+            .join(format!("{}.lock", id));
+        let Ok(file) = OpenOptions::new().write(true).open(path) else {
+            return false;
+        };
+        matches!(file.try_lock(), Err(TryLockError::WouldBlock))
+    }
+
+    fn saved_jobs(&self) -> Result<Vec<Job>, String> {
+        let path = self.data_dir.join(JOBS_FILE);
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+        let contents = std::fs::read(&path).map_err(|e| format!("Failed to read jobs: {}", e))?;
+        serde_json::from_slice(&contents).map_err(|e| format!("Failed to parse jobs: {}", e))
+    }
+
+    fn save_jobs(&self, jobs: &[Job]) -> Result<(), String> {
+        std::fs::create_dir_all(&self.data_dir)
+            .map_err(|e| format!("Failed to create data directory: {}", e))?;
+        let contents = serde_json::to_vec_pretty(jobs)
+            .map_err(|e| format!("Failed to serialize jobs: {}", e))?;
+        std::fs::write(self.data_dir.join(JOBS_FILE), contents)
+            .map_err(|e| format!("Failed to save jobs: {}", e))
+    }
+}
+
+fn find_job<'a>(jobs: &'a mut [Job], id: &str) -> Result<&'a mut Job, String> {
+    jobs.iter_mut()
+        .find(|j| j.id == id)
+        .ok_or_else(|| format!("Unknown job {}", id))
+}
+
+fn set_state(job: &mut Job, state: JobState) {
+    job.state = state;
+    job.updated_at = Utc::now();
+}
+
+/// Starts a worker running the pending jobs for the device with `udid` in the background.
+pub fn spawn_job_worker(handle: AppHandle, udid: String) {
+    tauri::async_runtime::spawn(async move {
+        let core = handle.state::<IloaderCore>();
+        if let Err(e) = core.run_jobs(&udid, &handle).await {
+            warn!("Job worker for {} stopped: {}", udid, e);
+        }
+    });
+}
+
+#[tauri::command]
+pub fn list_jobs(core: State<'_, IloaderCore>) -> Result<Vec<Job>, String> {
+    core.jobs()
+}
+
+#[tauri::command]
+pub fn enqueue_job(
+    handle: AppHandle,
+    core: State<'_, IloaderCore>,
+    kind: JobKind,
+    udid: Option<String>,
+) -> Result<Job, String> {
+    let udid = match udid {
+        Some(udid) => udid,
+        None => core.selected_device()?.uuid,
+    };
+    let job = core.enqueue_job(kind, udid)?;
+    spawn_job_worker(handle, job.udid.clone());
+    Ok(job)
+}
+
+#[tauri::command]
+pub fn cancel_job(core: State<'_, IloaderCore>, id: String) -> Result<(), String> {
+    core.cancel_job(&id)
+}
+
+#[tauri::command]
+pub fn retry_job(
+    handle: AppHandle,
+    core: State<'_, IloaderCore>,
+    id: String,
+) -> Result<Job, String> {
+    let job = core.retry_job(&id)?;
+    spawn_job_worker(handle, job.udid.clone());
+    Ok(job)
+}
+
+#[tauri::command]
+pub fn clear_finished_jobs(core: State<'_, IloaderCore>) -> Result<(), String> {
+    core.clear_finished_jobs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn sideload_kind() -> JobKind {
+        JobKind::Sideload {
+            app_path: "app.ipa".to_string(),
+            overrides: AppOverrides::default(),
+        }
+    }
+
+    #[test]
+    fn jobs_running_in_another_process_are_cancelled_there() {
+        let data_dir = TempDir::new();
+        // Each core has its own operations, like separate processes sharing the data directory
+        let other = IloaderCore::new(data_dir.0.clone(), std::env::temp_dir());
+        let core = IloaderCore::new(data_dir.0.clone(), std::env::temp_dir());
+
+        let job = core
+            .enqueue_job(sideload_kind(), "udid".to_string())
+            .unwrap();
+        let owner = other.own_job(&job.id).unwrap().unwrap();
+        other
+            .with_jobs(|jobs| {
+                set_state(find_job(jobs, &job.id)?, JobState::Running);
+                Ok(())
+            })
+            .unwrap();
+
+        let error = core.cancel_job(&job.id).unwrap_err();
+        assert!(error.contains("another iloader process"), "{}", error);
+        assert_eq!(core.jobs().unwrap()[0].state, JobState::Running);
+
+        // Once the owner is gone the job was left running, so it can be cancelled here
+        drop(owner);
+        core.cancel_job(&job.id).unwrap();
+        assert_eq!(core.jobs().unwrap()[0].state, JobState::Cancelled);
+    }
+
+    #[test]
+    fn pending_jobs_are_cancelled() {
+        let data_dir = TempDir::new();
+        let core = IloaderCore::new(data_dir.0.clone(), std::env::temp_dir());
+
+        let job = core
+            .enqueue_job(sideload_kind(), "udid".to_string())
+            .unwrap();
+        core.cancel_job(&job.id).unwrap();
+        assert_eq!(core.jobs().unwrap()[0].state, JobState::Cancelled);
+        assert!(core.cancel_job(&job.id).is_err());
+    }
+}
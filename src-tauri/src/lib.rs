@@ -1,24 +1,64 @@
 #[macro_use]
-mod account;
+pub mod account;
 #[macro_use]
-mod device;
+pub mod device;
 #[macro_use]
-mod sideload;
+pub mod sideload;
 #[macro_use]
-mod pairing;
+pub mod pairing;
+#[macro_use]
+pub mod network;
+#[macro_use]
+pub mod download;
+#[macro_use]
+pub mod sources;
+#[macro_use]
+pub mod altstore;
+#[macro_use]
+pub mod ipa;
+#[macro_use]
+pub mod expiry;
+#[macro_use]
+pub mod history;
+#[macro_use]
+pub mod jobs;
+#[macro_use]
+pub mod refresh;
+mod iloader_core;
 mod logging;
-mod operation;
+pub mod operation;
+pub mod quota;
+#[cfg(test)]
+mod test_util;
+
+pub use iloader_core::{IloaderCore, PromptAbort, Prompter};
 
 use crate::{
     account::{
         delete_account, delete_app_id, get_certificates, invalidate_account, list_app_ids,
         logged_in_as, login_new, login_stored, reset_anisette_state, revoke_certificate,
     },
-    device::{DeviceInfoMutex, list_devices, set_selected_device},
+    altstore::{fetch_altstore_feed, install_feed_app_operation},
+    device::{
+        DeviceEvent, get_device_details, list_devices, set_selected_device, spawn_device_watcher,
+        trust_device_cmd,
+    },
+    download::{clear_cache, get_exported_proxy, set_http_config},
+    expiry::list_installed_apps,
+    history::{clear_history, get_history_entry, list_history},
+    ipa::inspect_ipa,
+    jobs::{cancel_job, clear_finished_jobs, enqueue_job, list_jobs, retry_job, spawn_job_worker},
+    network::{add_network_device, remove_network_device},
+    operation::cancel_operation,
     pairing::{export_pairing_cmd, installed_pairing_apps, place_pairing_cmd},
-    sideload::{SideloaderMutex, install_sidestore_operation, sideload_operation},
+    refresh::{set_refresh_config, spawn_refresh_check, spawn_refresh_scheduler},
+    sideload::{
+        install_sidestore_operation, list_batch_apps, sideload_batch_operation,
+        sideload_many_operation, sideload_operation, sideload_url_operation,
+    },
+    sources::{add_sidestore_source, list_sidestore_sources, remove_sidestore_source},
 };
-use tauri::Manager;
+use tauri::{Emitter, Manager};
 use tracing_subscriber::{Layer, Registry, fmt, layer::SubscriberExt, util::SubscriberInitExt};
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -89,8 +129,50 @@ pub fn run() {
                 );
             }));
 
-            app.manage(DeviceInfoMutex::new(None));
-            app.manage(SideloaderMutex::new(None));
+            // Jobs, history, tracked apps and the download cache live here, so don't fall back to
+            // a relative path
+            let data_dir = match app.path().app_data_dir() {
+                Ok(dir) => dir,
+                Err(e) => {
+                    tracing::error!("Failed to get app data directory: {}", e);
+                    dirs::data_dir()
+                        .map(|dir| dir.join(&app.config().identifier))
+                        .ok_or("Failed to get a data directory to store iloader's data in")?
+                }
+            };
+            let temp_dir = app
+                .path()
+                .temp_dir()
+                .unwrap_or_else(|_| std::env::temp_dir());
+            app.manage(IloaderCore::new(data_dir, temp_dir));
+
+            match app.state::<IloaderCore>().resume_jobs() {
+                Ok(udids) => {
+                    for udid in udids {
+                        spawn_job_worker(app.handle().clone(), udid);
+                    }
+                }
+                Err(e) => tracing::error!("Failed to resume jobs: {}", e),
+            }
+
+            spawn_refresh_scheduler(app.handle().clone());
+
+            let handle = app.handle().clone();
+            spawn_device_watcher(move |event| {
+                let (event_name, device) = match event {
+                    DeviceEvent::Attached(device) => {
+                        spawn_refresh_check(handle.clone(), Some(device.uuid.clone()));
+                        ("device-attached", device)
+                    }
+                    DeviceEvent::Detached(device) => {
+                        if handle.state::<IloaderCore>().forget_device(&device) {
+                            tracing::warn!("Selected device {} was disconnected", device.name);
+                        }
+                        ("device-detached", device)
+                    }
+                };
+                let _ = handle.emit(event_name, device);
+            });
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
@@ -100,9 +182,37 @@ pub fn run() {
             login_stored,
             delete_account,
             list_devices,
+            get_device_details,
+            trust_device_cmd,
+            add_network_device,
+            remove_network_device,
             sideload_operation,
+            sideload_many_operation,
+            sideload_url_operation,
+            sideload_batch_operation,
+            list_batch_apps,
+            inspect_ipa,
+            cancel_operation,
+            clear_cache,
+            set_http_config,
+            get_exported_proxy,
             set_selected_device,
             install_sidestore_operation,
+            list_sidestore_sources,
+            add_sidestore_source,
+            remove_sidestore_source,
+            fetch_altstore_feed,
+            install_feed_app_operation,
+            enqueue_job,
+            list_jobs,
+            cancel_job,
+            retry_job,
+            clear_finished_jobs,
+            list_history,
+            get_history_entry,
+            clear_history,
+            list_installed_apps,
+            set_refresh_config,
             get_certificates,
             revoke_certificate,
             list_app_ids,
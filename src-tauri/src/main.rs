@@ -12,6 +12,13 @@ fn main() {
         }
     }
 
+    // isideload's anisette and Apple clients can only be given a proxy through the environment
+    if let Some(proxy) = iloader_lib::download::saved_proxy() {
+        unsafe {
+            iloader_lib::download::export_proxy_env(&proxy);
+        }
+    }
+
     // To be quite honest, I have no idea how ring has made its way into the dependence tree in some cases.
     rustls::crypto::aws_lc_rs::default_provider()
         .install_default()
@@ -0,0 +1,408 @@
+use std::{
+    net::IpAddr,
+    path::{Path, PathBuf},
+    pin::Pin,
+    time::Duration,
+};
+
+use idevice::{
+    Idevice, IdeviceError,
+    pairing_file::PairingFile,
+    provider::{IdeviceProvider, TcpProvider},
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::State;
+
+use crate::{
+    device::{DeviceInfo, DeviceStatus, device_status, usbmuxd_devices},
+    iloader_core::IloaderCore,
+};
+
+const NETWORK_DEVICES_FILE: &str = "network_devices.json";
+const PAIRING_FILES_DIR: &str = "network_devices";
+pub(crate) const LOCKDOWN_PORT: u16 = 62078;
+/// Network devices get ids from here up so they never clash with usbmuxd's.
+const NETWORK_DEVICE_ID_BASE: u32 = 0x8000_0000;
+/// How long a saved network device has to answer before it is shown as unreachable
+const NETWORK_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How to reach a device over the network instead of through usbmuxd.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkTarget {
+    /// IP address or hostname of the device
+    pub address: String,
+    pub pairing_file: PathBuf,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+struct NetworkDeviceEntry {
+    udid: String,
+    name: String,
+    address: String,
+}
+
+pub async fn tcp_provider(target: &NetworkTarget, udid: &str) -> Result<TcpProvider, String> {
+    let mut pairing_file = PairingFile::read_from_file(&target.pairing_file)
+        .map_err(|e| format!("Failed to read pairing file: {}", e))?;
+    if pairing_file.udid.is_none() {
+        pairing_file.udid = Some(udid.to_string());
+    }
+
+    Ok(TcpProvider {
+        addr: resolve(&target.address).await?,
+        pairing_file,
+        label: "iloader".to_string(),
+    })
+}
+
+/// A [`TcpProvider`] that reaches lockdown on another port, see [`IloaderCore::lockdown_port`].
+#[derive(Debug)]
+struct ProbeProvider {
+    tcp: TcpProvider,
+    lockdown_port: u16,
+}
+
+impl IdeviceProvider for ProbeProvider {
+    fn connect(
+        &self,
+        port: u16,
+    ) -> Pin<Box<dyn Future<Output = Result<Idevice, IdeviceError>> + Send>> {
+        let port = if port == LOCKDOWN_PORT {
+            self.lockdown_port
+        } else {
+            port
+        };
+        self.tcp.connect(port)
+    }
+
+    fn label(&self) -> &str {
+        self.tcp.label()
+    }
+
+    fn get_pairing_file(
+        &self,
+    ) -> Pin<Box<dyn Future<Output = Result<PairingFile, IdeviceError>> + Send>> {
+        self.tcp.get_pairing_file()
+    }
+}
+
+/// The id of the network device with `udid`, the same every time so removing another device
+/// doesn't change it.
+fn network_device_id(udid: &str) -> u32 {
+    let digest = Sha256::digest(udid.as_bytes());
+    let hash = u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]]);
+    NETWORK_DEVICE_ID_BASE | (hash & !NETWORK_DEVICE_ID_BASE)
+}
+
+async fn resolve(address: &str) -> Result<IpAddr, String> {
+    if let Ok(ip) = address.parse::<IpAddr>() {
+        return Ok(ip);
+    }
+    tokio::net::lookup_host((address, LOCKDOWN_PORT))
+        .await
+        .map_err(|e| format!("Failed to resolve {}: {}", address, e))?
+        .next()
+        .map(|a| a.ip())
+        .ok_or_else(|| format!("{} did not resolve to an address", address))
+}
+
+impl IloaderCore {
+    /// USB devices from usbmuxd followed by the saved network devices.
+    pub async fn devices(&self) -> Result<Vec<DeviceInfo>, String> {
+        let network = self.network_devices().await?;
+        match usbmuxd_devices().await {
+            Ok(mut devices) => {
+                devices.extend(network);
+                Ok(devices)
+            }
+            // Network devices don't need usbmuxd, so only fail if there is nothing else to show
+            Err(e) if network.is_empty() => Err(e),
+            Err(_) => Ok(network),
+        }
+    }
+
+    pub async fn network_devices(&self) -> Result<Vec<DeviceInfo>, String> {
+        let entries = self.network_device_entries()?;
+        let futures: Vec<_> = entries
+            .iter()
+            .map(|entry| self.network_device_info(entry))
+            .collect();
+        Ok(futures::future::join_all(futures).await)
+    }
+
+    /// The saved network device with `udid`, or `None` if there is none. Only that device is
+    /// probed.
+    pub async fn network_device(&self, udid: &str) -> Result<Option<DeviceInfo>, String> {
+        let entries = self.network_device_entries()?;
+        let Some(entry) = entries.iter().find(|e| e.udid == udid) else {
+            return Ok(None);
+        };
+        Ok(Some(self.network_device_info(entry).await))
+    }
+
+    /// Saves a device reachable at `address`, copying its pairing file into the data directory.
+    ///
+    /// The device is saved even if it can't be reached right now, the returned status says why.
+    pub async fn add_network_device(
+        &self,
+        address: String,
+        pairing_file: &Path,
+    ) -> Result<DeviceInfo, String> {
+        let contents = tokio::fs::read(pairing_file)
+            .await
+            .map_err(|e| format!("Failed to read pairing file: {}", e))?;
+        let udid = PairingFile::from_bytes(&contents)
+            .map_err(|e| format!("Invalid pairing file: {}", e))?
+            .udid
+            .ok_or_else(|| "The pairing file does not contain the device's UDID".to_string())?;
+
+        let dest = self.network_pairing_file(&udid);
+        if let Some(parent) = dest.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| format!("Failed to create pairing file directory: {}", e))?;
+        }
+        tokio::fs::write(&dest, &contents)
+            .await
+            .map_err(|e| format!("Failed to save pairing file: {}", e))?;
+
+        let mut entries = self.network_device_entries()?;
+        let mut entry = NetworkDeviceEntry {
+            udid: udid.clone(),
+            name: address.clone(),
+            address,
+        };
+        let index = match entries.iter().position(|e| e.udid == udid) {
+            Some(i) => {
+                entries[i] = entry.clone();
+                i
+            }
+            None => {
+                entries.push(entry.clone());
+                entries.len() - 1
+            }
+        };
+
+        let info = self.network_device_info(&entry).await;
+        // Keep the address as the name until the device has been reached once
+        if info.status == DeviceStatus::Ready {
+            entry.name = info.name.clone();
+        }
+        entries[index] = entry;
+        self.save_network_device_entries(&entries)?;
+
+        Ok(info)
+    }
+
+    pub async fn remove_network_device(&self, udid: &str) -> Result<(), String> {
+        let mut entries = self.network_device_entries()?;
+        entries.retain(|e| e.udid != udid);
+        self.save_network_device_entries(&entries)?;
+
+        let pairing_file = self.network_pairing_file(udid);
+        if pairing_file.exists() {
+            tokio::fs::remove_file(pairing_file)
+                .await
+                .map_err(|e| format!("Failed to remove pairing file: {}", e))?;
+        }
+        Ok(())
+    }
+
+    async fn network_device_info(&self, entry: &NetworkDeviceEntry) -> DeviceInfo {
+        let network = NetworkTarget {
+            address: entry.address.clone(),
+            pairing_file: self.network_pairing_file(&entry.udid),
+        };
+        let mut info = DeviceInfo {
+            name: entry.name.clone(),
+            id: network_device_id(&entry.udid),
+            uuid: entry.udid.clone(),
+            connection_type: "Network".to_string(),
+            status: DeviceStatus::Ready,
+            network: Some(network.clone()),
+        };
+        let probe = async {
+            match tcp_provider(&network, &entry.udid).await {
+                Ok(tcp) => {
+                    let provider = ProbeProvider {
+                        tcp,
+                        lockdown_port: self.lockdown_port,
+                    };
+                    device_status(&provider, &mut info).await
+                }
+                Err(e) => info.status = DeviceStatus::LockdownError(e),
+            }
+        };
+        // idevice connects without a timeout, so an offline device would otherwise hold up every
+        // device lookup until the OS gives up on the connection
+        if tokio::time::timeout(NETWORK_PROBE_TIMEOUT, probe)
+            .await
+            .is_err()
+        {
+            info.status = DeviceStatus::LockdownError(format!("{} did not respond", entry.address));
+        }
+        info
+    }
+
+    fn network_pairing_file(&self, udid: &str) -> PathBuf {
+        self.data_dir
+            .join(PAIRING_FILES_DIR)
+            .join(format!("{}.plist", udid))
+    }
+
+    fn network_device_entries(&self) -> Result<Vec<NetworkDeviceEntry>, String> {
+        let path = self.data_dir.join(NETWORK_DEVICES_FILE);
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+        let contents =
+            std::fs::read(&path).map_err(|e| format!("Failed to read network devices: {}", e))?;
+        serde_json::from_slice(&contents)
+            .map_err(|e| format!("Failed to parse network devices: {}", e))
+    }
+
+    fn save_network_device_entries(&self, entries: &[NetworkDeviceEntry]) -> Result<(), String> {
+        std::fs::create_dir_all(&self.data_dir)
+            .map_err(|e| format!("Failed to create data directory: {}", e))?;
+        let contents = serde_json::to_vec_pretty(entries)
+            .map_err(|e| format!("Failed to serialize network devices: {}", e))?;
+        std::fs::write(self.data_dir.join(NETWORK_DEVICES_FILE), contents)
+            .map_err(|e| format!("Failed to save network devices: {}", e))
+    }
+}
+
+#[tauri::command]
+pub async fn add_network_device(
+    core: State<'_, IloaderCore>,
+    address: String,
+    pairing_file: String,
+) -> Result<DeviceInfo, String> {
+    core.add_network_device(address, Path::new(&pairing_file))
+        .await
+}
+
+#[tauri::command]
+pub async fn remove_network_device(
+    core: State<'_, IloaderCore>,
+    udid: String,
+) -> Result<(), String> {
+    core.remove_network_device(&udid).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use plist::{Dictionary, Value};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+
+    use super::*;
+    use crate::test_util::TempDir;
+
+    const UDID: &str = "00008030-001A2B3C4D5E802E";
+
+    fn write_pairing_file(path: &Path) {
+        let cert =
+            Value::Data(b"-----BEGIN CERTIFICATE-----\nAAAA\n-----END CERTIFICATE-----\n".to_vec());
+        let mut pairing_file = Dictionary::new();
+        for key in [
+            "DeviceCertificate",
+            "HostCertificate",
+            "HostPrivateKey",
+            "RootCertificate",
+            "RootPrivateKey",
+        ] {
+            pairing_file.insert(key.to_string(), cert.clone());
+        }
+        pairing_file.insert("SystemBUID".to_string(), "SYSTEM-BUID".into());
+        pairing_file.insert("HostID".to_string(), "HOST-ID".into());
+        pairing_file.insert("WiFiMACAddress".to_string(), "00:00:00:00:00:00".into());
+        pairing_file.insert("UDID".to_string(), UDID.into());
+        Value::Dictionary(pairing_file).to_file_xml(path).unwrap();
+    }
+
+    /// Answers lockdown requests like a device that doesn't trust this computer.
+    async fn serve_untrusted_lockdown(mut stream: TcpStream) {
+        loop {
+            let mut len = [0u8; 4];
+            if stream.read_exact(&mut len).await.is_err() {
+                return;
+            }
+            let mut request = vec![0; u32::from_be_bytes(len) as usize];
+            stream.read_exact(&mut request).await.unwrap();
+            let request: Dictionary = plist::from_bytes(&request).unwrap();
+
+            let mut response = Dictionary::new();
+            let key = request.get("Key").and_then(Value::as_string);
+            match request.get("Request").and_then(Value::as_string) {
+                Some("GetValue") if key == Some("DeviceName") => {
+                    response.insert("Value".to_string(), "Test iPhone".into())
+                }
+                Some("GetValue") => response.insert("Value".to_string(), "17.0".into()),
+                _ => response.insert("Error".to_string(), "InvalidHostID".into()),
+            };
+            let mut body = Vec::new();
+            plist::to_writer_xml(&mut body, &response).unwrap();
+            stream
+                .write_all(&(body.len() as u32).to_be_bytes())
+                .await
+                .unwrap();
+            stream.write_all(&body).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn network_devices_are_probed_through_lockdown() {
+        let data_dir = TempDir::new();
+        let pairing_file = data_dir.0.join("pairing.plist");
+        write_pairing_file(&pairing_file);
+        let mut core = IloaderCore::new(data_dir.0.clone(), std::env::temp_dir());
+
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        core.lockdown_port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                tokio::spawn(serve_untrusted_lockdown(stream));
+            }
+        });
+        let device = core
+            .add_network_device("127.0.0.1".to_string(), &pairing_file)
+            .await
+            .unwrap();
+        assert_eq!(device.uuid, UDID);
+        assert_eq!(device.name, "Test iPhone");
+        assert_eq!(device.status, DeviceStatus::NotTrusted);
+        server.abort();
+        let _ = server.await;
+
+        // A device that accepts the connection but never answers is shown as unreachable
+        // instead of holding up the lookup
+        let listener = TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+        core.lockdown_port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let mut connections = Vec::new();
+            loop {
+                connections.push(listener.accept().await.unwrap().0);
+            }
+        });
+        let started = Instant::now();
+        let device = core.device(Some(UDID)).await.unwrap();
+        assert!(matches!(device.status, DeviceStatus::LockdownError(_)));
+        assert!(started.elapsed() < NETWORK_PROBE_TIMEOUT + Duration::from_secs(2));
+        server.abort();
+    }
+
+    #[test]
+    fn network_device_ids_depend_only_on_the_udid() {
+        let id = network_device_id(UDID);
+        assert!(id >= NETWORK_DEVICE_ID_BASE);
+        assert_eq!(id, network_device_id(UDID));
+        assert_ne!(id, network_device_id("00008030-000000000000002E"));
+    }
+}
@@ -1,22 +1,207 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
 use serde::Serialize;
-use tauri::{Emitter, Window};
+use tauri::{AppHandle, Emitter, State, Window};
+use tokio_util::sync::CancellationToken;
+
+use tracing::warn;
+
+use crate::{
+    history::HistoryEntry,
+    iloader_core::{IloaderCore, PromptAbort},
+    sideload::SigningInfo,
+};
+
+/// Receives the progress of an [`Operation`].
+pub trait ProgressSink: Send + Sync {
+    fn update(&self, operation_id: &str, update: OperationUpdate<'_>) -> Result<(), String>;
+}
+
+impl ProgressSink for Window {
+    fn update(&self, operation_id: &str, update: OperationUpdate<'_>) -> Result<(), String> {
+        self.emit(&format!("operation_{}", operation_id), update)
+            .map_err(|_| "Failed to emit status to frontend".to_string())
+    }
+}
+
+/// Reports to every window, for operations not started from one.
+impl ProgressSink for AppHandle {
+    fn update(&self, operation_id: &str, update: OperationUpdate<'_>) -> Result<(), String> {
+        self.emit(&format!("operation_{}", operation_id), update)
+            .map_err(|_| "Failed to emit status to frontend".to_string())
+    }
+}
+
+/// Cancellation tokens of the running operations by id.
+pub type OperationRegistry = Mutex<HashMap<String, CancellationToken>>;
+
+pub const CANCELLED: &str = "Operation cancelled";
 
 pub struct Operation<'a> {
+    /// Unique per run, progress is sent to the sink under it
     id: String,
-    window: &'a Window,
+    /// What the operation does, e.g. `sideload`
+    name: String,
+    sink: &'a dyn ProgressSink,
+    token: CancellationToken,
+    registry: Option<&'a OperationRegistry>,
+    /// Where the operation is recorded, see [`Operation::record_history`]
+    history: Option<(&'a IloaderCore, Mutex<HistoryEntry>)>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperationUpdate<'a> {
+    pub update_type: &'a str,
+    pub step_id: &'a str,
+    pub extra_details: Option<String>,
+    /// Only set for `progress` updates
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub progress: Option<Progress>,
 }
 
+/// How far along a transfer within a step is.
 #[derive(Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
-struct OperationUpdate<'a> {
-    update_type: &'a str,
-    step_id: &'a str,
-    extra_details: Option<String>,
+pub struct Progress {
+    pub done: u64,
+    /// `None` if the size isn't known, e.g. when the server doesn't send a length
+    pub total: Option<u64>,
+    pub bytes_per_second: u64,
 }
 
 impl<'a> Operation<'a> {
-    pub fn new(id: String, window: &'a Window) -> Operation<'a> {
-        Operation { id, window }
+    pub fn new(id: String, name: &str, sink: &'a dyn ProgressSink) -> Operation<'a> {
+        Operation {
+            id,
+            name: name.to_string(),
+            sink,
+            token: CancellationToken::new(),
+            registry: None,
+            history: None,
+        }
+    }
+
+    /// Like [`Operation::new`], but the operation can be cancelled through `registry` by its id
+    /// until it is dropped. Fails if an operation with the same id is already registered.
+    pub fn registered(
+        id: String,
+        name: &str,
+        sink: &'a dyn ProgressSink,
+        registry: &'a OperationRegistry,
+    ) -> Result<Operation<'a>, String> {
+        let mut op = Operation::new(id, name, sink);
+        {
+            let mut registry = registry.lock().unwrap();
+            if registry.contains_key(&op.id) {
+                return Err(format!("Operation {} is already running", op.id));
+            }
+            registry.insert(op.id.clone(), op.token.clone());
+        }
+        op.registry = Some(registry);
+        Ok(op)
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Records the operation and its steps to `core`'s history, see [`IloaderCore::history`].
+    pub fn record_history(&mut self, core: &'a IloaderCore) {
+        let entry = HistoryEntry::new(self.name.clone(), self.id.clone(), core.logged_in_as());
+        if let Err(e) = core.save_history_entry(&entry) {
+            warn!("Failed to record operation: {}", e);
+        }
+        self.history = Some((core, Mutex::new(entry)));
+    }
+
+    /// Notes in the history that the operation targets the device with `udid`.
+    pub fn record_device(&self, udid: &str) {
+        self.record(|entry| {
+            if !entry.devices.iter().any(|d| d == udid) {
+                entry.devices.push(udid.to_string());
+            }
+        });
+    }
+
+    /// Notes in the history that the operation installs the app at `app`, a path or URL.
+    pub fn record_app(&self, app: &str) {
+        self.record(|entry| entry.apps.push(app.to_string()));
+    }
+
+    /// Notes in the history what an installed app was signed with.
+    pub fn record_signing(&self, signing: &SigningInfo) {
+        self.record(|entry| entry.signed.push(signing.clone()));
+    }
+
+    fn record(&self, f: impl FnOnce(&mut HistoryEntry)) {
+        if let Some((_, entry)) = &self.history {
+            f(&mut entry.lock().unwrap());
+        }
+    }
+
+    fn save_history(&self, f: impl FnOnce(&mut HistoryEntry)) {
+        if let Some((core, entry)) = &self.history {
+            let mut entry = entry.lock().unwrap();
+            f(&mut entry);
+            if let Err(e) = core.save_history_entry(&entry) {
+                warn!("Failed to record operation: {}", e);
+            }
+        }
+    }
+
+    /// Sends `update` to the sink and, for step changes, records it.
+    fn send(&self, update: OperationUpdate<'_>) -> Result<(), String> {
+        if update.update_type != "progress" {
+            self.save_history(|entry| {
+                entry.update_step(
+                    update.update_type,
+                    update.step_id,
+                    update.extra_details.as_deref(),
+                )
+            });
+        }
+        self.sink.update(&self.id, update)
+    }
+
+    pub fn cancel_token(&self) -> &CancellationToken {
+        &self.token
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    /// Runs `fut` as part of step `id`, failing the step if it errors. If the operation is
+    /// cancelled first, `fut` is dropped and the step is reported as cancelled.
+    ///
+    /// Prompts asked while `fut` runs are aborted when this operation is cancelled, see
+    /// [`PromptAbort::current`].
+    pub async fn run<T>(
+        &self,
+        id: &str,
+        fut: impl Future<Output = Result<T, String>>,
+    ) -> Result<T, String> {
+        tokio::select! {
+            biased;
+            _ = self.token.cancelled() => self.cancelled(id),
+            res = PromptAbort::scope(self.token.clone(), fut) => self.fail_if_err(id, res),
+        }
+    }
+
+    pub fn cancelled<T>(&self, id: &str) -> Result<T, String> {
+        self.send(OperationUpdate {
+            update_type: "cancelled",
+            step_id: id,
+            extra_details: None,
+            progress: None,
+        })?;
+        Err(CANCELLED.to_string())
     }
 
     pub fn move_on(&self, old_id: &str, new_id: &str) -> Result<(), String> {
@@ -25,45 +210,55 @@ impl<'a> Operation<'a> {
     }
 
     pub fn start(&self, id: &str) -> Result<(), String> {
-        self.window
-            .emit(
-                &format!("operation_{}", self.id),
-                OperationUpdate {
-                    update_type: "started",
-                    step_id: id,
-                    extra_details: None,
-                },
-            )
-            .map_err(|_| "Failed to emit status to frontend".to_string())
+        if self.is_cancelled() {
+            return self.cancelled(id);
+        }
+        self.send(OperationUpdate {
+            update_type: "started",
+            step_id: id,
+            extra_details: None,
+            progress: None,
+        })
     }
 
     pub fn complete(&self, id: &str) -> Result<(), String> {
-        self.window
-            .emit(
-                &format!("operation_{}", self.id),
-                OperationUpdate {
-                    update_type: "finished",
-                    step_id: id,
-                    extra_details: None,
-                },
-            )
-            .map_err(|_| "Failed to emit status to frontend".to_string())
+        self.send(OperationUpdate {
+            update_type: "finished",
+            step_id: id,
+            extra_details: None,
+            progress: None,
+        })
     }
 
     pub fn fail<T>(&self, id: &str, error: String) -> Result<T, String> {
-        self.window
-            .emit(
-                &format!("operation_{}", self.id),
-                OperationUpdate {
-                    update_type: "failed",
-                    step_id: id,
-                    extra_details: Some(error.clone()),
-                },
-            )
-            .map_err(|_| "Failed to emit status to frontend".to_string())?;
+        self.send(OperationUpdate {
+            update_type: "failed",
+            step_id: id,
+            extra_details: Some(error.clone()),
+            progress: None,
+        })?;
         Err(error)
     }
 
+    pub fn progress(&self, id: &str, progress: Progress) -> Result<(), String> {
+        self.send(OperationUpdate {
+            update_type: "progress",
+            step_id: id,
+            extra_details: None,
+            progress: Some(progress),
+        })
+    }
+
+    /// Reports the bytes transferred in step `id`, see [`ProgressTracker`].
+    pub fn progress_tracker(&self, id: &str) -> ProgressTracker<'_> {
+        ProgressTracker {
+            op: self,
+            step_id: id.to_string(),
+            started: Instant::now(),
+            last_update: None,
+        }
+    }
+
     pub fn fail_if_err<T>(&self, id: &str, res: Result<T, String>) -> Result<T, String> {
         match res {
             Ok(t) => Ok(t),
@@ -71,3 +266,56 @@ impl<'a> Operation<'a> {
         }
     }
 }
+
+impl Drop for Operation<'_> {
+    fn drop(&mut self) {
+        self.save_history(HistoryEntry::finish);
+        if let Some(registry) = self.registry {
+            registry.lock().unwrap().remove(&self.id);
+        }
+    }
+}
+
+#[tauri::command]
+pub fn cancel_operation(core: State<'_, IloaderCore>, id: String) -> Result<(), String> {
+    core.cancel_operation(&id)
+}
+
+/// Turns byte counts into `progress` updates with a transfer rate, sent at most every
+/// [`ProgressTracker::INTERVAL`] so fast transfers don't flood the frontend.
+pub struct ProgressTracker<'a> {
+    op: &'a Operation<'a>,
+    step_id: String,
+    started: Instant,
+    last_update: Option<Instant>,
+}
+
+impl ProgressTracker<'_> {
+    const INTERVAL: Duration = Duration::from_millis(250);
+
+    pub fn update(&mut self, done: u64, total: Option<u64>) {
+        let now = Instant::now();
+        let finished = total.is_some_and(|t| done >= t);
+        if !finished && self.last_update.is_some_and(|l| now - l < Self::INTERVAL) {
+            return;
+        }
+        self.last_update = Some(now);
+
+        let elapsed = (now - self.started).as_secs_f64();
+        let bytes_per_second = if elapsed > 0.0 {
+            (done as f64 / elapsed) as u64
+        } else {
+            0
+        };
+        if let Err(e) = self.op.progress(
+            &self.step_id,
+            Progress {
+                done,
+                total,
+                bytes_per_second,
+            },
+        ) {
+            warn!("Failed to report progress: {}", e);
+        }
+    }
+}
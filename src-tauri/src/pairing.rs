@@ -3,13 +3,17 @@ use std::collections::HashMap;
 // used https://github.com/jkcoxson/idevice_pair/ as a guide
 use idevice::{
     IdeviceService, house_arrest::HouseArrestClient, installation_proxy::InstallationProxyClient,
-    lockdown::LockdownClient, pairing_file::PairingFile, usbmuxd::UsbmuxdConnection,
+    lockdown::LockdownClient, pairing_file::PairingFile, provider::IdeviceProvider,
 };
 use serde::Serialize;
 use tauri::{AppHandle, State};
 use tauri_plugin_dialog::DialogExt;
 
-use crate::device::{DeviceInfo, DeviceInfoMutex, get_provider, get_provider_from_connection};
+use crate::{
+    device::{DeviceInfo, get_provider},
+    iloader_core::IloaderCore,
+    sources::SideStoreSource,
+};
 
 const PAIRING_APPS: &[(&str, &str)] = &[
     ("SideStore", "ALTPairingFile.mobiledevicepairing"),
@@ -28,20 +32,17 @@ const PAIRING_APPS: &[(&str, &str)] = &[
     ("ByeTunes", "pairing file/pairingFile.plist"),
 ];
 
-async fn pairing_file(
-    device: DeviceInfo,
-    usbmuxd: &mut UsbmuxdConnection,
-) -> Result<PairingFile, String> {
+pub async fn pairing_file(device: DeviceInfo) -> Result<PairingFile, String> {
     let provider = get_provider(&device).await?;
 
-    let mut pairing_file = usbmuxd.get_pair_record(&provider.udid).await.map_err(|e| {
+    let mut pairing_file = provider.get_pairing_file().await.map_err(|e| {
         format!(
             "Failed to get pairing record for device {}: {}",
             device.name, e
         )
     })?;
 
-    pairing_file.udid = Some(provider.udid.clone());
+    pairing_file.udid = Some(device.uuid.clone());
 
     let mut lc = LockdownClient::connect(&provider)
         .await
@@ -67,13 +68,9 @@ pub async fn place_pairing(
     bundle_id: String,
     path: String,
 ) -> Result<(), String> {
-    let mut usbmuxd = UsbmuxdConnection::default()
-        .await
-        .map_err(|e| format!("Failed to connect to usbmuxd: {}", e))?;
-
-    let provider = get_provider_from_connection(&device, &mut usbmuxd).await?;
+    let provider = get_provider(&device).await?;
 
-    let pairing_file = pairing_file(device, &mut usbmuxd).await?;
+    let pairing_file = pairing_file(device).await?;
 
     let house_arrest_client = HouseArrestClient::connect(&provider)
         .await
@@ -114,44 +111,53 @@ pub async fn place_pairing(
     Ok(())
 }
 
+impl IloaderCore {
+    /// Apps known to use a pairing file that are installed on the device with `udid`, or the
+    /// selected device.
+    pub async fn pairing_apps(&self, udid: Option<&str>) -> Result<Vec<PairingAppInfo>, String> {
+        let device = self.device(udid).await?;
+        list_pairing_apps(&device).await
+    }
+
+    pub async fn place_pairing(
+        &self,
+        udid: Option<&str>,
+        bundle_id: String,
+        path: String,
+    ) -> Result<(), String> {
+        let device = self.device(udid).await?;
+        place_pairing(device, bundle_id, path).await
+    }
+
+    /// The pairing file of the device with `udid`, or the selected device, serialized as a plist.
+    pub async fn serialized_pairing_file(&self, udid: Option<&str>) -> Result<Vec<u8>, String> {
+        let device = self.device(udid).await?;
+
+        pairing_file(device)
+            .await?
+            .serialize()
+            .map_err(|e| format!("Failed to serialize pairing file: {}", e))
+    }
+}
+
 #[tauri::command]
 pub async fn place_pairing_cmd(
-    device_state: State<'_, DeviceInfoMutex>,
+    core: State<'_, IloaderCore>,
     bundle_id: String,
     path: String,
+    udid: Option<String>,
 ) -> Result<(), String> {
-    let device = {
-        let device_guard = device_state.lock().unwrap();
-        match &*device_guard {
-            Some(d) => d.clone(),
-            None => return Err("No device selected".to_string()),
-        }
-    };
-
-    place_pairing(device, bundle_id, path).await
+    core.place_pairing(udid.as_deref(), bundle_id, path).await
 }
 
 // prompt for a location to save the pairing file, then export it there. This is for advanced users who want to use the pairing file with other tools, or just want a backup of it. Normal users should use the "Place" button next to the app they want to pair with instead, which will transfer the pairing file automatically.
 #[tauri::command]
 pub async fn export_pairing_cmd(
-    device_state: State<'_, DeviceInfoMutex>,
+    core: State<'_, IloaderCore>,
     app: AppHandle,
+    udid: Option<String>,
 ) -> Result<(), String> {
-    let device = {
-        let device_guard = device_state.lock().unwrap();
-        match &*device_guard {
-            Some(d) => d.clone(),
-            None => return Err("No device selected".to_string()),
-        }
-    };
-
-    let pairing_file = {
-        let mut usbmuxd = UsbmuxdConnection::default()
-            .await
-            .map_err(|e| format!("Failed to connect to usbmuxd: {}", e))?;
-
-        pairing_file(device, &mut usbmuxd).await?
-    };
+    let pairing_file = core.serialized_pairing_file(udid.as_deref()).await?;
 
     let save_path = app
         .dialog()
@@ -164,14 +170,9 @@ pub async fn export_pairing_cmd(
     if let Some(save_path) = save_path
         && let Some(save_path) = save_path.as_path()
     {
-        tokio::fs::write(
-            save_path,
-            &pairing_file
-                .serialize()
-                .map_err(|e| format!("Failed to serialize pairing file: {}", e))?,
-        )
-        .await
-        .map_err(|e| format!("Failed to write pairing file: {}", e))?;
+        tokio::fs::write(save_path, &pairing_file)
+            .await
+            .map_err(|e| format!("Failed to write pairing file: {}", e))?;
 
         Ok(())
     } else {
@@ -179,7 +180,7 @@ pub async fn export_pairing_cmd(
     }
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct PairingAppInfo {
     pub name: String,
@@ -189,16 +190,14 @@ pub struct PairingAppInfo {
 
 #[tauri::command]
 pub async fn installed_pairing_apps(
-    device_state: State<'_, DeviceInfoMutex>,
+    core: State<'_, IloaderCore>,
+    udid: Option<String>,
 ) -> Result<Vec<PairingAppInfo>, String> {
-    let device = {
-        let device_guard = device_state.lock().unwrap();
-        match &*device_guard {
-            Some(d) => d.clone(),
-            None => return Err("No device selected".to_string()),
-        }
-    };
-    let provider = get_provider(&device).await?;
+    core.pairing_apps(udid.as_deref()).await
+}
+
+pub async fn list_pairing_apps(device: &DeviceInfo) -> Result<Vec<PairingAppInfo>, String> {
+    let provider = get_provider(device).await?;
     let mut installation_proxy = InstallationProxyClient::connect(&provider)
         .await
         .map_err(|e| format!("Failed to connect to installation proxy: {}", e))?;
@@ -237,9 +236,10 @@ pub async fn installed_pairing_apps(
     Ok(result)
 }
 
+/// Finds the installed app from `source` by its display name.
 pub async fn get_sidestore_info(
     device: DeviceInfo,
-    live_container: bool,
+    source: &SideStoreSource,
 ) -> Result<Option<PairingAppInfo>, String> {
     let provider = get_provider(&device).await?;
     let mut installation_proxy = InstallationProxyClient::connect(&provider)
@@ -257,15 +257,11 @@ pub async fn get_sidestore_info(
             .and_then(|x| x.get("CFBundleDisplayName").and_then(|x| x.as_string()))
             .ok_or("Failed to parse installed apps".to_string())?;
 
-        if n == "SideStore" || (live_container && n == "LiveContainer") {
+        if n == source.display_name {
             return Ok(Some(PairingAppInfo {
                 name: n.to_string(),
                 bundle_id: bundle_id.to_string(),
-                path: PAIRING_APPS
-                    .iter()
-                    .find(|(name, _)| name == &n)
-                    .map(|(_, path)| path.to_string())
-                    .unwrap_or_default(),
+                path: source.pairing_path.clone(),
             }));
         }
     }
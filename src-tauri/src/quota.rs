@@ -0,0 +1,229 @@
+use std::path::Path;
+
+use isideload::dev::app_ids::{AppId, AppIdsApi, ListAppIdsResponse};
+use serde::Serialize;
+use tracing::info;
+
+use crate::{
+    iloader_core::{IloaderCore, PromptAbort},
+    ipa::{AppOverrides, IpaInfo, ipa_info},
+};
+
+/// Passed to [`crate::Prompter::app_ids_to_delete`] when an app needs more new App IDs than
+/// the account has left.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AppIdShortfall {
+    pub app_name: String,
+    /// App IDs the app needs that aren't registered yet
+    pub required: usize,
+    pub available: u64,
+    /// The App IDs registered to the team
+    pub app_ids: Vec<AppId>,
+}
+
+impl IloaderCore {
+    /// Checks that the account can register every App ID the IPA at `app_path` needs once
+    /// `overrides` are applied, which is one for the app and one for each extension minus those
+    /// already registered by an earlier install. Free accounts can only register 10 App IDs
+    /// every 7 days.
+    ///
+    /// When there aren't enough left the prompter is asked which App IDs to delete, and the
+    /// check fails with what to do if that doesn't free up enough.
+    pub async fn check_app_id_quota(
+        &self,
+        app_path: &Path,
+        overrides: &AppOverrides,
+    ) -> Result<(), String> {
+        // isideload also accepts app bundles, which we don't inspect
+        if !app_path.is_file() {
+            return Ok(());
+        }
+        let path = app_path.to_path_buf();
+        let app = tokio::task::spawn_blocking(move || ipa_info(&path))
+            .await
+            .map_err(|e| e.to_string())??
+            .with_overrides(overrides);
+
+        let mut prompted = false;
+        loop {
+            let (team_id, response) = self.app_ids_with_team().await?;
+            let Some(available) = response.available_quantity else {
+                return Ok(());
+            };
+
+            let Some(required) = app_id_shortfall(&app, &team_id, &response.app_ids, available)
+            else {
+                return Ok(());
+            };
+
+            let message = format!(
+                "{} needs {} new App ID{} but only {} {} available. Delete App IDs you no longer \
+                 need, or wait for them to expire; free accounts can register 10 App IDs every \
+                 7 days.",
+                app.name,
+                required,
+                if required == 1 { "" } else { "s" },
+                available,
+                if available == 1 { "is" } else { "are" },
+            );
+            if prompted {
+                return Err(message);
+            }
+            prompted = true;
+
+            let prompter = self.prompter()?;
+            let shortfall = AppIdShortfall {
+                app_name: app.name.clone(),
+                required,
+                available,
+                app_ids: response.app_ids,
+            };
+            let abort = PromptAbort::current();
+            let to_delete = tokio::task::spawn_blocking(move || {
+                abort.sync_scope(|| prompter.app_ids_to_delete(shortfall))
+            })
+            .await
+            .map_err(|e| e.to_string())?;
+            let Some(to_delete) = to_delete.filter(|ids| !ids.is_empty()) else {
+                return Err(message);
+            };
+            for app_id_id in &to_delete {
+                self.delete_app_id(app_id_id).await?;
+            }
+            info!("Deleted {} App IDs to make room", to_delete.len());
+        }
+    }
+
+    async fn app_ids_with_team(&self) -> Result<(String, ListAppIdsResponse), String> {
+        let mut sideloader = self.lock_sideloader().await?;
+        let team = sideloader
+            .get_mut()
+            .get_team()
+            .await
+            .map_err(|e| e.to_string())?;
+        let response = sideloader
+            .get_mut()
+            .get_dev_session()
+            .list_app_ids(&team, None)
+            .await
+            .map_err(|e| format!("Failed to list App IDs: {}", e))?;
+        Ok((team.team_id, response))
+    }
+}
+
+/// How many new App IDs `app` needs when the team already has `registered`, or `None` if the
+/// `available` ones are enough.
+fn app_id_shortfall(
+    app: &IpaInfo,
+    team_id: &str,
+    registered: &[AppId],
+    available: u64,
+) -> Option<usize> {
+    // isideload registers the app as `<bundle id>.<team id>`, and extensions under it
+    let main_id = format!("{}.{}", app.bundle_id, team_id);
+    let required = std::iter::once(main_id.clone())
+        .chain(app.extensions.iter().filter_map(|extension| {
+            extension
+                .bundle_id
+                .strip_prefix(&app.bundle_id)
+                .filter(|suffix| !suffix.is_empty())
+                .map(|suffix| format!("{}{}", main_id, suffix))
+        }))
+        .filter(|identifier| !registered.iter().any(|a| &a.identifier == identifier))
+        .count();
+    (required as u64 > available).then_some(required)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ipa::IpaExtension;
+
+    use super::*;
+
+    const TEAM_ID: &str = "TEAM123456";
+
+    fn app(extensions: &[&str]) -> IpaInfo {
+        IpaInfo {
+            bundle_id: "com.example.app".to_string(),
+            name: "App".to_string(),
+            version: None,
+            build: None,
+            minimum_os_version: None,
+            extensions: extensions
+                .iter()
+                .map(|bundle_id| IpaExtension {
+                    bundle_id: bundle_id.to_string(),
+                    name: String::new(),
+                })
+                .collect(),
+            entitlements: plist::Dictionary::new(),
+            icon: None,
+            app_ids_required: 1 + extensions.len(),
+        }
+    }
+
+    fn app_id(identifier: &str) -> AppId {
+        AppId {
+            app_id_id: identifier.to_string(),
+            identifier: identifier.to_string(),
+            name: identifier.to_string(),
+            features: plist::Dictionary::new(),
+            expiration_date: None,
+        }
+    }
+
+    #[test]
+    fn app_and_extensions_each_need_an_app_id() {
+        let app = app(&["com.example.app.widget", "com.example.app.share"]);
+        assert_eq!(app_id_shortfall(&app, TEAM_ID, &[], 3), None);
+        assert_eq!(app_id_shortfall(&app, TEAM_ID, &[], 2), Some(3));
+        assert_eq!(app_id_shortfall(&app, TEAM_ID, &[], 0), Some(3));
+    }
+
+    #[test]
+    fn registered_app_ids_are_not_counted() {
+        let app = app(&["com.example.app.widget", "com.example.app.share"]);
+        let registered = [
+            app_id("com.example.app.TEAM123456"),
+            app_id("com.example.app.TEAM123456.widget"),
+            app_id("com.example.other.TEAM123456"),
+        ];
+        assert_eq!(app_id_shortfall(&app, TEAM_ID, &registered, 1), None);
+        assert_eq!(app_id_shortfall(&app, TEAM_ID, &registered, 0), Some(1));
+
+        let registered = [
+            app_id("com.example.app.TEAM123456"),
+            app_id("com.example.app.TEAM123456.widget"),
+            app_id("com.example.app.TEAM123456.share"),
+        ];
+        assert_eq!(app_id_shortfall(&app, TEAM_ID, &registered, 0), None);
+    }
+
+    #[test]
+    fn overridden_bundle_ids_are_counted_under_the_new_prefix() {
+        let app = app(&["com.example.app.widget", "com.example.app.share"]);
+        let registered = [
+            app_id("com.example.app.TEAM123456"),
+            app_id("com.example.app.TEAM123456.widget"),
+            app_id("com.example.copy.TEAM123456.widget"),
+        ];
+        assert_eq!(app_id_shortfall(&app, TEAM_ID, &registered, 0), Some(1));
+
+        // A second copy can't reuse the original's App IDs
+        let copy = app.with_overrides(&AppOverrides {
+            bundle_id: Some("com.example.copy".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(app_id_shortfall(&copy, TEAM_ID, &registered, 2), None);
+        assert_eq!(app_id_shortfall(&copy, TEAM_ID, &registered, 1), Some(2));
+    }
+
+    #[test]
+    fn extensions_outside_the_app_bundle_id_are_not_counted() {
+        // isideload rejects these, so they never get an App ID
+        let app = app(&["com.other.widget", "com.example.app"]);
+        assert_eq!(app_id_shortfall(&app, TEAM_ID, &[], 1), None);
+        assert_eq!(app_id_shortfall(&app, TEAM_ID, &[], 0), Some(1));
+    }
+}
@@ -0,0 +1,145 @@
+use std::{path::Path, time::Duration};
+
+use serde::Deserialize;
+use tauri::{AppHandle, Manager, State};
+use tracing::{info, warn};
+
+use crate::{
+    iloader_core::IloaderCore,
+    jobs::{Job, JobKind, JobState, spawn_job_worker},
+};
+
+/// How often connected devices are checked for apps to refresh, besides when they connect
+const REFRESH_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// Apps installed more recently aren't refreshed again, in case signing didn't extend their
+/// expiry, e.g. because Apple returned the same provisioning profile
+const MIN_REFRESH_AGE_HOURS: i64 = 24;
+
+/// When installed apps are re-signed before they expire, see [`IloaderCore::queue_refreshes`].
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RefreshConfig {
+    pub enabled: bool,
+    /// Apps expiring within this many days are refreshed
+    pub days_before_expiry: u32,
+}
+
+impl Default for RefreshConfig {
+    fn default() -> Self {
+        RefreshConfig {
+            enabled: false,
+            days_before_expiry: 2,
+        }
+    }
+}
+
+impl IloaderCore {
+    pub fn set_refresh_config(&self, config: RefreshConfig) {
+        *self.refresh_config.lock().unwrap() = config;
+    }
+
+    /// Queues a job re-signing and reinstalling each tracked app (see
+    /// [`IloaderCore::installed_apps`]) that expires soon on a connected device, or only on the
+    /// device with `udid`. Returns the queued jobs, which run once a worker is started for their
+    /// device.
+    ///
+    /// The app is installed again the way it was first installed, e.g. downloaded again from its
+    /// URL, and signed with the same bundle ID, so the install replaces the old one and keeps
+    /// its data. Apps installed in the last day, or with an unfinished, failed or cancelled
+    /// refresh job, are skipped until that job is retried or cleared.
+    pub async fn queue_refreshes(&self, udid: Option<&str>) -> Result<Vec<Job>, String> {
+        let config = self.refresh_config.lock().unwrap().clone();
+        if !config.enabled {
+            return Ok(vec![]);
+        }
+        // Checks triggered at the same time would otherwise queue the same app twice
+        let _lock = self.refresh_lock.lock().await;
+        let connected: Vec<String> = self
+            .devices()
+            .await?
+            .into_iter()
+            .map(|d| d.uuid)
+            .filter(|d| udid.is_none_or(|udid| udid == d))
+            .collect();
+        if connected.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let threshold = chrono::Duration::days(config.days_before_expiry.into());
+        let now = chrono::Utc::now();
+        let jobs = self.jobs()?;
+        let mut queued = Vec::new();
+        for expiring in self.installed_apps().await? {
+            let app = expiring.app;
+            if !connected.contains(&app.udid)
+                || app.expires_at - now > threshold
+                || now - app.installed_at < chrono::Duration::hours(MIN_REFRESH_AGE_HOURS)
+            {
+                continue;
+            }
+            let kind = match app.origin {
+                Some(kind) => kind,
+                // Tracked before origins were recorded, the download may have been evicted
+                None if self.is_cached_download(Path::new(&app.app_path)) => {
+                    warn!(
+                        "Not refreshing {} on {}, it was installed from the download cache",
+                        app.bundle_id, app.udid
+                    );
+                    continue;
+                }
+                None => JobKind::Sideload {
+                    app_path: app.app_path,
+                    overrides: app.overrides,
+                },
+            };
+            let already_queued = jobs.iter().any(|job| {
+                job.kind == kind && job.udid == app.udid && job.state != JobState::Succeeded
+            });
+            if already_queued {
+                continue;
+            }
+            info!(
+                "Refreshing {} on {}, it expires {}",
+                app.bundle_id, app.udid, app.expires_at
+            );
+            queued.push(self.enqueue_job(kind, app.udid)?);
+        }
+        Ok(queued)
+    }
+}
+
+/// Queues refreshes for the device with `udid`, or every connected device, in the background
+/// and starts workers to run them.
+pub fn spawn_refresh_check(handle: AppHandle, udid: Option<String>) {
+    tauri::async_runtime::spawn(async move {
+        let core = handle.state::<IloaderCore>();
+        match core.queue_refreshes(udid.as_deref()).await {
+            Ok(jobs) => {
+                for job in jobs {
+                    spawn_job_worker(handle.clone(), job.udid);
+                }
+            }
+            Err(e) => warn!("Failed to check for apps to refresh: {}", e),
+        }
+    });
+}
+
+/// Checks every connected device for apps to refresh every [`REFRESH_CHECK_INTERVAL`], for
+/// devices that stay connected, e.g. over Wi-Fi.
+pub fn spawn_refresh_scheduler(handle: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(REFRESH_CHECK_INTERVAL).await;
+            spawn_refresh_check(handle.clone(), None);
+        }
+    });
+}
+
+#[tauri::command]
+pub fn set_refresh_config(handle: AppHandle, core: State<'_, IloaderCore>, config: RefreshConfig) {
+    let enabled = config.enabled;
+    core.set_refresh_config(config);
+    if enabled {
+        spawn_refresh_check(handle, None);
+    }
+}
@@ -1,174 +1,858 @@
-use std::{path::PathBuf, sync::Mutex};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
 
 use crate::{
-    device::{get_provider, DeviceInfoMutex},
+    device::{DeviceInfo, get_provider},
+    iloader_core::{IloaderCore, Prompter},
+    ipa::{AppOverrides, rewrite_ipa},
+    jobs::JobKind,
     operation::Operation,
     pairing::{get_sidestore_info, place_pairing},
+    sources::SideStoreSource,
+};
+use chrono::{DateTime, Utc};
+use idevice::{
+    IdeviceService, afc::AfcClient, installation_proxy::InstallationProxyClient,
+    provider::IdeviceProvider,
 };
-use isideload::sideload::{application::SpecialApp, sideloader::Sideloader};
-use tauri::{AppHandle, Manager, State, Window};
+use isideload::{dev::devices::DevicesApi, sideload::sideloader::Sideloader};
+use serde::{Deserialize, Serialize};
+use tauri::{State, Window};
+use tokio::sync::OwnedMutexGuard;
+use tracing::{debug, warn};
+use x509_certificate::X509Certificate;
 
-pub type SideloaderMutex = Mutex<Option<Sideloader>>;
+/// The logged in account. Its `Sideloader` is shared by all operations, which only hold it
+/// while talking to Apple so installs to different devices can run at the same time.
+#[derive(Clone)]
+pub struct LoggedInAccount {
+    pub email: String,
+    sideloader: Arc<tokio::sync::Mutex<Sideloader>>,
+    /// Answers the questions asked while installing with this account
+    pub(crate) prompter: Arc<dyn Prompter>,
+}
 
-pub struct SideloaderGuard<'a> {
-    state: &'a SideloaderMutex,
-    sideloader: Option<Sideloader>,
+impl LoggedInAccount {
+    pub fn new(sideloader: Sideloader, prompter: Arc<dyn Prompter>) -> Self {
+        LoggedInAccount {
+            email: sideloader.get_email().to_string(),
+            sideloader: Arc::new(tokio::sync::Mutex::new(sideloader)),
+            prompter,
+        }
+    }
 }
 
-impl<'a> SideloaderGuard<'a> {
-    pub fn take(state: &'a SideloaderMutex) -> Result<Self, String> {
-        let mut guard = state.lock().unwrap();
-        let sideloader = guard.take().ok_or_else(|| "Not logged in".to_string())?;
+pub type SideloaderMutex = Mutex<Option<LoggedInAccount>>;
+
+/// Exclusive use of the logged in account's `Sideloader`, waiting for other operations to
+/// release it. Logging out while it is held doesn't affect the holder.
+pub struct SideloaderGuard {
+    sideloader: OwnedMutexGuard<Sideloader>,
+}
+
+impl SideloaderGuard {
+    pub async fn lock(state: &SideloaderMutex) -> Result<Self, String> {
+        let account = state
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or_else(|| "Not logged in".to_string())?;
         Ok(Self {
-            state,
-            sideloader: Some(sideloader),
+            sideloader: account.sideloader.lock_owned().await,
         })
     }
 
     pub fn get_mut(&mut self) -> &mut Sideloader {
-        self.sideloader
-            .as_mut()
-            .expect("Sideloader should be present")
+        &mut self.sideloader
     }
 }
 
-impl Drop for SideloaderGuard<'_> {
+/// An IPA copied to a unique path for one operation, see [`IloaderCore::stage_app`]. The copy
+/// is removed on drop, so it is also cleaned up when the operation is cancelled.
+struct StagedApp(Option<PathBuf>);
+
+impl StagedApp {
+    /// The staged copy, or `original` if it is an app bundle that wasn't copied.
+    fn path<'a>(&'a self, original: &'a Path) -> &'a Path {
+        self.0.as_deref().unwrap_or(original)
+    }
+}
+
+impl Drop for StagedApp {
     fn drop(&mut self) {
-        let mut guard = self.state.lock().unwrap();
-        *guard = self.sideloader.take();
+        if let Some(path) = &self.0
+            && let Err(e) = std::fs::remove_file(path)
+        {
+            warn!("Failed to remove staged app: {}", e);
+        }
     }
 }
 
-pub async fn sideload(
-    device_state: State<'_, DeviceInfoMutex>,
-    sideloader_state: State<'_, SideloaderMutex>,
-    app_path: String,
-) -> Result<Option<SpecialApp>, String> {
-    let device = {
-        let device_lock = device_state.lock().unwrap();
-        match &*device_lock {
-            Some(d) => d.clone(),
-            None => return Err("No device selected".to_string()),
+/// The folder isideload extracts an IPA into to sign it, removed on drop. App bundles are signed
+/// in place, so there is nothing to remove for them.
+struct ExtractedApp(Option<PathBuf>);
+
+impl ExtractedApp {
+    /// isideload extracts IPAs to `<file name>_extracted` in the system temp directory.
+    fn for_app(app_path: &Path) -> Self {
+        ExtractedApp(
+            app_path
+                .file_name()
+                .filter(|_| app_path.is_file())
+                .map(|name| {
+                    std::env::temp_dir().join(format!("{}_extracted", name.to_string_lossy()))
+                }),
+        )
+    }
+}
+
+impl Drop for ExtractedApp {
+    fn drop(&mut self) {
+        if let Some(path) = &self.0
+            && path.exists()
+            && let Err(e) = std::fs::remove_dir_all(path)
+        {
+            warn!("Failed to remove temporary signed app: {}", e);
         }
+    }
+}
+
+struct SignedApp {
+    path: PathBuf,
+    signing: SigningInfo,
+    /// Registration errors by UDID of the devices the app was not signed for
+    unregistered: HashMap<String, String>,
+    /// Where `path` was extracted to
+    _extracted: ExtractedApp,
+}
+
+/// What an app was signed with, read from its embedded provisioning profile.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SigningInfo {
+    /// The bundle ID the app was installed under, which includes the team ID
+    pub bundle_id: String,
+    pub team_id: String,
+    /// Serial numbers of the certificates the profile allows, as shown by Apple
+    pub certificate_serials: Vec<String>,
+    /// When the provisioning profile expires and the app stops launching
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Reads the bundle ID and provisioning profile of the signed app bundle at `bundle_dir`.
+pub fn signing_info(bundle_dir: &Path) -> Result<SigningInfo, String> {
+    let info: plist::Dictionary = plist::from_file(bundle_dir.join("Info.plist"))
+        .map_err(|e| format!("Failed to read signed Info.plist: {}", e))?;
+    let bundle_id = info
+        .get("CFBundleIdentifier")
+        .and_then(|v| v.as_string())
+        .ok_or_else(|| "Signed app has no bundle ID".to_string())?
+        .to_string();
+
+    let profile = std::fs::read(bundle_dir.join("embedded.mobileprovision"))
+        .map_err(|e| format!("Failed to read provisioning profile: {}", e))?;
+    // The profile is a plist wrapped in a CMS signature, which doesn't need verifying here
+    let start = find_bytes(&profile, b"<?xml");
+    let end = find_bytes(&profile, b"</plist>").map(|end| end + b"</plist>".len());
+    let profile: plist::Dictionary = match (start, end) {
+        (Some(start), Some(end)) if start < end => plist::from_bytes(&profile[start..end])
+            .map_err(|e| format!("Failed to parse provisioning profile: {}", e))?,
+        _ => return Err("Invalid provisioning profile".to_string()),
     };
 
-    let provider = get_provider(&device).await?;
+    let team_id = profile
+        .get("TeamIdentifier")
+        .and_then(|v| v.as_array())
+        .and_then(|ids| ids.first())
+        .and_then(|id| id.as_string())
+        .ok_or_else(|| "Provisioning profile has no team".to_string())?
+        .to_string();
+    let expires_at = profile
+        .get("ExpirationDate")
+        .and_then(|v| v.as_date())
+        .map(|date| DateTime::<Utc>::from(std::time::SystemTime::from(date)))
+        .ok_or_else(|| "Provisioning profile has no expiration date".to_string())?;
+    let certificate_serials = profile
+        .get("DeveloperCertificates")
+        .and_then(|v| v.as_array())
+        .map(|certs| {
+            certs
+                .iter()
+                .filter_map(|cert| cert.as_data())
+                .filter_map(|der| X509Certificate::from_der(der).ok())
+                .map(|cert| {
+                    // Apple shows serials in hex without leading zeros
+                    hex::encode_upper(cert.serial_number_asn1().as_slice())
+                        .trim_start_matches('0')
+                        .to_string()
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(SigningInfo {
+        bundle_id,
+        team_id,
+        certificate_serials,
+        expires_at,
+    })
+}
+
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
 
-    let mut sideloader = SideloaderGuard::take(&sideloader_state)?;
+/// The outcome of installing one app with [`IloaderCore::sideload_batch`].
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AppInstallResult {
+    pub path: String,
+    /// `None` if the install succeeded
+    pub error: Option<String>,
+}
 
-    sideloader
-        .get_mut()
-        .install_app(&provider, app_path.into(), false)
+/// The apps to install for `paths`: IPAs and app bundles as given, and for any other directory
+/// the IPAs directly inside it, sorted by name.
+pub fn batch_app_paths(paths: &[String]) -> Result<Vec<String>, String> {
+    let mut apps = Vec::new();
+    for path in paths {
+        let path = Path::new(path);
+        if !path.is_dir() || path.extension().is_some_and(|e| e == "app") {
+            apps.push(path.to_string_lossy().to_string());
+            continue;
+        }
+        let mut ipas: Vec<String> = std::fs::read_dir(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|p| p.is_file() && p.extension().is_some_and(|e| e == "ipa"))
+            .map(|p| p.to_string_lossy().to_string())
+            .collect();
+        ipas.sort();
+        apps.extend(ipas);
+    }
+    Ok(apps)
+}
+
+/// The outcome of installing on one device with [`IloaderCore::sideload_many`].
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceInstallResult {
+    pub udid: String,
+    /// `None` if the device wasn't connected
+    pub name: Option<String>,
+    /// `None` if the install succeeded
+    pub error: Option<String>,
+}
+
+/// Uploads a signed app to the device and installs it. Does what isideload's `install_app`
+/// does, but reports the bytes uploaded through `on_progress(done, total)`.
+async fn install_signed(
+    provider: &impl IdeviceProvider,
+    device: &DeviceInfo,
+    signed_app_path: &Path,
+    mut on_progress: impl FnMut(u64, Option<u64>),
+) -> Result<(), String> {
+    let files = app_files(signed_app_path)?;
+    let total = files.iter().map(|(_, size)| size.unwrap_or(0)).sum();
+
+    let mut afc_client = AfcClient::connect(provider)
+        .await
+        .map_err(|e| format!("Failed to connect to AFC: {}", e))?;
+    let staging_dir = format!(
+        "PublicStaging/{}",
+        signed_app_path
+            .file_name()
+            .ok_or_else(|| "Invalid signed app path".to_string())?
+            .to_string_lossy()
+    );
+    afc_client
+        .mk_dir(&staging_dir)
         .await
-        .map_err(|e| e.to_string())
+        .map_err(|e| format!("Failed to create staging directory: {}", e))?;
+
+    let mut done = 0;
+    on_progress(done, Some(total));
+    for (relative, size) in &files {
+        let afc_path = format!("{}/{}", staging_dir, relative);
+        if size.is_none() {
+            afc_client
+                .mk_dir(&afc_path)
+                .await
+                .map_err(|e| format!("Failed to create {}: {}", afc_path, e))?;
+            continue;
+        }
+
+        let bytes = tokio::fs::read(signed_app_path.join(relative))
+            .await
+            .map_err(|e| format!("Failed to read {}: {}", relative, e))?;
+        let mut file = afc_client
+            .open(&afc_path, idevice::afc::opcode::AfcFopenMode::WrOnly)
+            .await
+            .map_err(|e| format!("Failed to open {} on device: {}", afc_path, e))?;
+        for chunk in bytes.chunks(UPLOAD_CHUNK_SIZE) {
+            file.write_entire(chunk)
+                .await
+                .map_err(|e| format!("Failed to upload {}: {}", relative, e))?;
+            done += chunk.len() as u64;
+            on_progress(done, Some(total));
+        }
+        file.close()
+            .await
+            .map_err(|e| format!("Failed to close {}: {}", afc_path, e))?;
+    }
+
+    let mut instproxy_client = InstallationProxyClient::connect(provider)
+        .await
+        .map_err(|e| format!("Failed to connect to installation proxy: {}", e))?;
+    let mut options = plist::Dictionary::new();
+    options.insert("PackageType".to_string(), "Developer".into());
+    instproxy_client
+        .install_with_callback(
+            staging_dir,
+            Some(plist::Value::Dictionary(options)),
+            async |(percentage, _)| {
+                debug!("Installing to {}: {}%", device.uuid, percentage);
+            },
+            (),
+        )
+        .await
+        .map_err(|e| format!("Failed to install app on device: {}", e))
+}
+
+const UPLOAD_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Every entry under `root` as a `/` separated relative path, with directories (`None` size)
+/// listed before their contents.
+fn app_files(root: &Path) -> Result<Vec<(String, Option<u64>)>, String> {
+    let mut files = Vec::new();
+    let mut pending = vec![(root.to_path_buf(), String::new())];
+    while let Some((dir, prefix)) = pending.pop() {
+        let entries = std::fs::read_dir(&dir)
+            .map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+        for entry in entries {
+            let entry = entry.map_err(|e| format!("Failed to read {}: {}", dir.display(), e))?;
+            let metadata = entry
+                .metadata()
+                .map_err(|e| format!("Failed to read {}: {}", entry.path().display(), e))?;
+            let relative = format!("{}{}", prefix, entry.file_name().to_string_lossy());
+            if metadata.is_dir() {
+                files.push((relative.clone(), None));
+                pending.push((entry.path(), format!("{}/", relative)));
+            } else {
+                files.push((relative, Some(metadata.len())));
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Places the pairing file into the freshly installed app from `source`.
+pub async fn pair_sidestore(device: &DeviceInfo, source: &SideStoreSource) -> Result<(), String> {
+    match get_sidestore_info(device.clone(), source).await? {
+        Some(info) => place_pairing(device.clone(), info.bundle_id, info.path).await,
+        None => Err(format!(
+            "Could not find {}'s bundle ID",
+            source.display_name
+        )),
+    }
+}
+
+impl IloaderCore {
+    /// Applies `overrides` to `app_path`, signs it for `device` and installs it, returning what
+    /// it was signed with. `origin` is what refreshes run to install the app again.
+    ///
+    /// `on_progress(done, total)` is called with the bytes uploaded to the device.
+    pub(crate) async fn install_app(
+        &self,
+        device: &DeviceInfo,
+        app_path: &Path,
+        overrides: &AppOverrides,
+        origin: &JobKind,
+        on_progress: impl FnMut(u64, Option<u64>),
+    ) -> Result<SigningInfo, String> {
+        let provider = get_provider(device).await?;
+        let staged = self.stage_app(app_path, overrides).await?;
+
+        let signed = self
+            .sign_for_devices(std::slice::from_ref(device), staged.path(app_path))
+            .await?;
+        drop(staged);
+        install_signed(&provider, device, &signed.path, on_progress).await?;
+        if let Err(e) =
+            self.track_installed_app(device, app_path, overrides, origin, &signed.signing)
+        {
+            warn!("Failed to track installed app: {}", e);
+        }
+        Ok(signed.signing.clone())
+    }
+
+    /// isideload extracts IPAs next to a path derived from the file name, so give each
+    /// operation its own copy to keep concurrent installs of the same IPA apart. `overrides`
+    /// are applied to the copy.
+    async fn stage_app(
+        &self,
+        app_path: &Path,
+        overrides: &AppOverrides,
+    ) -> Result<StagedApp, String> {
+        overrides.validate()?;
+        if !app_path.is_file() {
+            if !overrides.is_empty() {
+                return Err("Overrides are only supported for IPA files".to_string());
+            }
+            return Ok(StagedApp(None));
+        }
+        let file_name = app_path
+            .file_name()
+            .ok_or_else(|| "Invalid app path".to_string())?
+            .to_string_lossy();
+        let staged = self
+            .temp_dir
+            .join(format!("{}-{}", uuid::Uuid::new_v4(), file_name));
+        let staged_app = StagedApp(Some(staged.clone()));
+        if overrides.is_empty() {
+            tokio::fs::copy(app_path, &staged)
+                .await
+                .map_err(|e| format!("Failed to copy app: {}", e))?;
+        } else {
+            let (app_path, overrides) = (app_path.to_path_buf(), overrides.clone());
+            tokio::task::spawn_blocking(move || rewrite_ipa(&app_path, &staged, &overrides))
+                .await
+                .map_err(|e| e.to_string())??;
+        }
+        Ok(staged_app)
+    }
+
+    /// Registers `devices` with the account's team, then signs the app once for every device
+    /// that could be registered. Fails if none could.
+    async fn sign_for_devices(
+        &self,
+        devices: &[DeviceInfo],
+        app_path: &Path,
+    ) -> Result<SignedApp, String> {
+        let mut sideloader = self.lock_sideloader().await?;
+        let sideloader = sideloader.get_mut();
+        let team = sideloader.get_team().await.map_err(|e| e.to_string())?;
+
+        let mut unregistered = HashMap::new();
+        for device in devices {
+            if let Err(e) = sideloader
+                .get_dev_session()
+                .ensure_device_registered(&team, &device.name, &device.uuid, None)
+                .await
+            {
+                unregistered.insert(
+                    device.uuid.clone(),
+                    format!("Failed to register device: {}", e),
+                );
+            }
+        }
+        if unregistered.len() == devices.len() {
+            return Err(unregistered
+                .into_values()
+                .next()
+                .unwrap_or_else(|| "No devices to sign for".to_string()));
+        }
+
+        let extracted = ExtractedApp::for_app(app_path);
+        let (path, _) = sideloader
+            .sign_app(app_path.to_path_buf(), Some(team), false)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        Ok(SignedApp {
+            signing: signing_info(&path)?,
+            path,
+            unregistered,
+            _extracted: extracted,
+        })
+    }
+
+    /// Signs the app at `app_path` once and installs it on every device in `udids` at the same
+    /// time. A device failing doesn't stop the others, the returned report has each outcome.
+    ///
+    /// Each device gets its own `install:<udid>` step after the shared `sign` step.
+    pub async fn sideload_many(
+        &self,
+        op: &Operation<'_>,
+        udids: &[String],
+        app_path: String,
+    ) -> Result<Vec<DeviceInstallResult>, String> {
+        op.start("sign")?;
+        let connected = op.run("sign", self.devices()).await?;
+
+        let mut results = Vec::new();
+        let mut devices = Vec::new();
+        for udid in udids {
+            match connected.iter().find(|d| &d.uuid == udid) {
+                Some(device) => devices.push(device.clone()),
+                None => results.push(DeviceInstallResult {
+                    udid: udid.clone(),
+                    name: None,
+                    error: Some(format!("Device {} is not connected", udid)),
+                }),
+            }
+        }
+        if devices.is_empty() {
+            return op.fail("sign", "No devices to install to".to_string());
+        }
+        for device in &devices {
+            op.record_device(&device.uuid);
+        }
+        op.record_app(&app_path);
+        let origin = JobKind::Sideload {
+            app_path: app_path.clone(),
+            overrides: AppOverrides::default(),
+        };
+
+        let app_path = PathBuf::from(app_path);
+        let signed = op
+            .run("sign", async {
+                let staged = self.stage_app(&app_path, &AppOverrides::default()).await?;
+                self.sign_for_devices(&devices, staged.path(&app_path))
+                    .await
+            })
+            .await?;
+        op.record_signing(&signed.signing);
+        op.complete("sign")?;
+
+        let installs = devices.iter().map(|device| {
+            let step = format!("install:{}", device.uuid);
+            let unregistered = signed.unregistered.get(&device.uuid).cloned();
+            let (signed_path, signing, app_path, origin) =
+                (&signed.path, &signed.signing, &app_path, &origin);
+            async move {
+                let install = async {
+                    if let Some(e) = unregistered {
+                        return Err(e);
+                    }
+                    let provider = get_provider(device).await?;
+                    let mut progress = op.progress_tracker(&step);
+                    install_signed(&provider, device, signed_path, |done, total| {
+                        progress.update(done, total)
+                    })
+                    .await?;
+                    if let Err(e) = self.track_installed_app(
+                        device,
+                        app_path,
+                        &AppOverrides::default(),
+                        origin,
+                        signing,
+                    ) {
+                        warn!("Failed to track installed app: {}", e);
+                    }
+                    Ok(())
+                };
+                let result = match op.start(&step) {
+                    Ok(()) => op.run(&step, install).await,
+                    Err(e) => Err(e),
+                };
+                let error = match result {
+                    Ok(()) => {
+                        // The app is installed, so keep it in the report even if this fails
+                        if let Err(e) = op.complete(&step) {
+                            warn!("Failed to report install on {}: {}", device.name, e);
+                        }
+                        None
+                    }
+                    Err(e) => Some(e),
+                };
+                DeviceInstallResult {
+                    udid: device.uuid.clone(),
+                    name: Some(device.name.clone()),
+                    error,
+                }
+            }
+        });
+        results.extend(futures::future::join_all(installs).await);
+
+        Ok(results)
+    }
+
+    /// Sign and install the app at `app_path` on the device with `udid`, or the selected device,
+    /// after applying `overrides`.
+    ///
+    /// The `app_ids` step first checks that the account has enough App IDs left for the app,
+    /// see [`IloaderCore::check_app_id_quota`].
+    pub async fn sideload(
+        &self,
+        op: &Operation<'_>,
+        udid: Option<&str>,
+        app_path: String,
+        overrides: &AppOverrides,
+    ) -> Result<(), String> {
+        op.start("app_ids")?;
+        let device = op.run("app_ids", self.device(udid)).await?;
+        op.record_device(&device.uuid);
+        op.record_app(&app_path);
+        op.fail_if_err("app_ids", overrides.validate())?;
+        op.run(
+            "app_ids",
+            self.check_app_id_quota(Path::new(&app_path), overrides),
+        )
+        .await?;
+        op.move_on("app_ids", "install")?;
+        let origin = JobKind::Sideload {
+            app_path: app_path.clone(),
+            overrides: overrides.clone(),
+        };
+        let mut progress = op.progress_tracker("install");
+        let signing = op
+            .run(
+                "install",
+                self.install_app(
+                    &device,
+                    Path::new(&app_path),
+                    overrides,
+                    &origin,
+                    |done, total| progress.update(done, total),
+                ),
+            )
+            .await?;
+        op.record_signing(&signing);
+        op.complete("install")?;
+        Ok(())
+    }
+
+    /// Installs the apps in `app_paths` (see [`batch_app_paths`]) one after another on the
+    /// device with `udid`, or the selected device. An app failing doesn't stop the others, the
+    /// returned report has each outcome.
+    ///
+    /// Each app gets its own `install:<index>` step, which checks the App ID quota before
+    /// installing like [`IloaderCore::sideload`].
+    pub async fn sideload_batch(
+        &self,
+        op: &Operation<'_>,
+        udid: Option<&str>,
+        app_paths: &[String],
+    ) -> Result<Vec<AppInstallResult>, String> {
+        let paths = batch_app_paths(app_paths)?;
+        if paths.is_empty() {
+            return Err("No apps to install".to_string());
+        }
+        op.start("device")?;
+        let device = op.run("device", self.device(udid)).await?;
+        op.record_device(&device.uuid);
+        op.complete("device")?;
+
+        let mut results = Vec::new();
+        for (i, path) in paths.iter().enumerate() {
+            let step = format!("install:{}", i);
+            op.start(&step)?;
+            op.record_app(path);
+            let install = async {
+                let origin = JobKind::Sideload {
+                    app_path: path.clone(),
+                    overrides: AppOverrides::default(),
+                };
+                let path = Path::new(path);
+                let overrides = AppOverrides::default();
+                self.check_app_id_quota(path, &overrides).await?;
+                let mut progress = op.progress_tracker(&step);
+                self.install_app(&device, path, &overrides, &origin, |done, total| {
+                    progress.update(done, total)
+                })
+                .await
+            };
+            let error = match op.run(&step, install).await {
+                Ok(signing) => {
+                    op.record_signing(&signing);
+                    op.complete(&step)?;
+                    None
+                }
+                Err(e) if op.is_cancelled() => return Err(e),
+                Err(e) => Some(e),
+            };
+            results.push(AppInstallResult {
+                path: path.clone(),
+                error,
+            });
+        }
+        Ok(results)
+    }
+
+    /// Download the IPA at `url`, then sign and install it on the device with `udid`, or the
+    /// selected device.
+    ///
+    /// `sha256` pins the expected digest of the IPA, see [`IloaderCore::download_ipa`].
+    pub async fn sideload_url(
+        &self,
+        op: &Operation<'_>,
+        udid: Option<&str>,
+        url: &str,
+        sha256: Option<&str>,
+    ) -> Result<(), String> {
+        op.start("download")?;
+        if !url.starts_with("https://") {
+            return op.fail(
+                "download",
+                format!("Only HTTPS URLs are supported: {}", url),
+            );
+        }
+        let device = op.run("download", self.device(udid)).await?;
+        op.record_device(&device.uuid);
+        op.record_app(url);
+
+        let mut progress = op.progress_tracker("download");
+        let dest = op
+            .run(
+                "download",
+                self.download_ipa(url, sha256, |done, total| progress.update(done, total)),
+            )
+            .await?;
+        op.move_on("download", "install")?;
+        // Refreshes download the app again, the cached copy may be gone by then
+        let origin = JobKind::SideloadUrl {
+            url: url.to_string(),
+            sha256: sha256.map(str::to_string),
+        };
+        let mut progress = op.progress_tracker("install");
+        let signing = op
+            .run(
+                "install",
+                self.install_app(
+                    &device,
+                    &dest.path,
+                    &AppOverrides::default(),
+                    &origin,
+                    |done, total| progress.update(done, total),
+                ),
+            )
+            .await?;
+        op.record_signing(&signing);
+        op.complete("install")?;
+        Ok(())
+    }
+
+    /// Download, install and pair SideStore from the source with id `source` (see
+    /// [`IloaderCore::sidestore_sources`]) on the device with `udid`, or the selected device.
+    ///
+    /// `sha256` pins the expected digest of the IPA, see [`IloaderCore::download_ipa`].
+    pub async fn install_sidestore(
+        &self,
+        op: &Operation<'_>,
+        udid: Option<&str>,
+        source: &str,
+        nightly: bool,
+        sha256: Option<&str>,
+    ) -> Result<(), String> {
+        op.start("download")?;
+        let origin = JobKind::InstallSidestore {
+            source: source.to_string(),
+            nightly,
+            sha256: sha256.map(str::to_string),
+        };
+        let device = op.run("download", self.device(udid)).await?;
+        let source = op.fail_if_err("download", self.sidestore_source(source))?;
+        let url = op.fail_if_err("download", source.url(nightly))?;
+        op.record_device(&device.uuid);
+        op.record_app(url);
+
+        let mut progress = op.progress_tracker("download");
+        let dest = op
+            .run(
+                "download",
+                self.download_ipa(url, sha256, |done, total| progress.update(done, total)),
+            )
+            .await?;
+        op.move_on("download", "install")?;
+        let mut progress = op.progress_tracker("install");
+        let signing = op
+            .run(
+                "install",
+                self.install_app(
+                    &device,
+                    &dest.path,
+                    &AppOverrides::default(),
+                    &origin,
+                    |done, total| progress.update(done, total),
+                ),
+            )
+            .await?;
+        op.record_signing(&signing);
+        op.move_on("install", "pairing")?;
+        op.run("pairing", pair_sidestore(&device, &source)).await?;
+        op.complete("pairing")?;
+        Ok(())
+    }
 }
 
 #[tauri::command]
 pub async fn sideload_operation(
     window: Window,
-    device_state: State<'_, DeviceInfoMutex>,
-    sideloader_state: State<'_, SideloaderMutex>,
+    core: State<'_, IloaderCore>,
+    operation_id: String,
     app_path: String,
+    udid: Option<String>,
+    overrides: Option<AppOverrides>,
 ) -> Result<(), String> {
-    let op = Operation::new("sideload".to_string(), &window);
-    op.start("install")?;
-    op.fail_if_err(
-        "install",
-        sideload(device_state, sideloader_state, app_path).await,
-    )?;
-    op.complete("install")?;
-    Ok(())
+    let op = core.operation_with_id(operation_id, "sideload", &window)?;
+    core.sideload(
+        &op,
+        udid.as_deref(),
+        app_path,
+        &overrides.unwrap_or_default(),
+    )
+    .await
 }
 
 #[tauri::command]
-pub async fn install_sidestore_operation(
-    handle: AppHandle,
+pub async fn sideload_url_operation(
     window: Window,
-    device_state: State<'_, DeviceInfoMutex>,
-    sideloader_state: State<'_, SideloaderMutex>,
-    nightly: bool,
-    live_container: bool,
+    core: State<'_, IloaderCore>,
+    operation_id: String,
+    url: String,
+    sha256: Option<String>,
+    udid: Option<String>,
 ) -> Result<(), String> {
-    let op = Operation::new("install_sidestore".to_string(), &window);
-    op.start("download")?;
-    // TODO: Cache & check version to avoid re-downloading
-    let (filename, url) = if live_container {
-        if nightly {
-            ("LiveContainerSideStore-Nightly.ipa", "https://github.com/LiveContainer/LiveContainer/releases/download/nightly/LiveContainer+SideStore.ipa")
-        } else {
-            ("LiveContainerSideStore.ipa", "https://github.com/LiveContainer/LiveContainer/releases/latest/download/LiveContainer+SideStore.ipa")
-        }
-    } else if nightly {
-        (
-            "SideStore-Nightly.ipa",
-            "https://github.com/SideStore/SideStore/releases/download/nightly/SideStore.ipa",
-        )
-    } else {
-        (
-            "SideStore.ipa",
-            "https://github.com/SideStore/SideStore/releases/latest/download/SideStore.ipa",
-        )
-    };
-
-    let dest = handle
-        .path()
-        .temp_dir()
-        .map_err(|e| format!("Failed to get temp dir: {:?}", e))?
-        .join(filename);
-    op.fail_if_err("download", download(url, &dest).await)?;
-    op.move_on("download", "install")?;
-    let device = {
-        let device_guard = device_state.lock().unwrap();
-        match &*device_guard {
-            Some(d) => d.clone(),
-            None => return op.fail("install", "No device selected".to_string()),
-        }
-    };
-    op.fail_if_err(
-        "install",
-        sideload(
-            device_state,
-            sideloader_state,
-            dest.to_string_lossy().to_string(),
-        )
-        .await,
-    )?;
-    op.move_on("install", "pairing")?;
-    let sidestore_info = op.fail_if_err(
-        "pairing",
-        get_sidestore_info(device.clone(), live_container).await,
-    )?;
-    if let Some(info) = sidestore_info {
-        op.fail_if_err(
-            "pairing",
-            place_pairing(device, info.bundle_id, info.path).await,
-        )?;
-    } else {
-        return op.fail(
-            "pairing",
-            "Could not find SideStore's bundle ID".to_string(),
-        );
-    }
-
-    op.complete("pairing")?;
-    Ok(())
-}
-
-pub async fn download(url: impl AsRef<str>, dest: &PathBuf) -> Result<(), String> {
-    let response = reqwest::get(url.as_ref())
+    let op = core.operation_with_id(operation_id, "sideload_url", &window)?;
+    core.sideload_url(&op, udid.as_deref(), &url, sha256.as_deref())
         .await
-        .map_err(|e| e.to_string())?;
-    if !response.status().is_success() {
-        return Err(format!(
-            "Failed to download file: HTTP {}",
-            response.status()
-        ));
-    }
+}
 
-    let bytes = response.bytes().await.map_err(|e| e.to_string())?;
-    tokio::fs::write(dest, &bytes)
-        .await
-        .map_err(|e| e.to_string())?;
+#[tauri::command]
+pub fn list_batch_apps(paths: Vec<String>) -> Result<Vec<String>, String> {
+    batch_app_paths(&paths)
+}
+
+#[tauri::command]
+pub async fn sideload_batch_operation(
+    window: Window,
+    core: State<'_, IloaderCore>,
+    operation_id: String,
+    app_paths: Vec<String>,
+    udid: Option<String>,
+) -> Result<Vec<AppInstallResult>, String> {
+    let op = core.operation_with_id(operation_id, "sideload_batch", &window)?;
+    core.sideload_batch(&op, udid.as_deref(), &app_paths).await
+}
+
+#[tauri::command]
+pub async fn sideload_many_operation(
+    window: Window,
+    core: State<'_, IloaderCore>,
+    operation_id: String,
+    app_path: String,
+    udids: Vec<String>,
+) -> Result<Vec<DeviceInstallResult>, String> {
+    let op = core.operation_with_id(operation_id, "sideload_many", &window)?;
+    core.sideload_many(&op, &udids, app_path).await
+}
 
-    Ok(())
+#[tauri::command]
+pub async fn install_sidestore_operation(
+    window: Window,
+    core: State<'_, IloaderCore>,
+    operation_id: String,
+    source: String,
+    nightly: bool,
+    udid: Option<String>,
+    sha256: Option<String>,
+) -> Result<(), String> {
+    let op = core.operation_with_id(operation_id, "install_sidestore", &window)?;
+    core.install_sidestore(&op, udid.as_deref(), &source, nightly, sha256.as_deref())
+        .await
 }
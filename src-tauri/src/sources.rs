@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::iloader_core::IloaderCore;
+
+const SOURCES_FILE: &str = "sidestore_sources.json";
+
+/// Where to get a SideStore build from and how to find it on the device afterwards.
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SideStoreSource {
+    pub id: String,
+    pub name: String,
+    pub stable_url: String,
+    pub nightly_url: Option<String>,
+    /// `CFBundleDisplayName` of the installed app, used to find it when placing the pairing file
+    pub display_name: String,
+    /// Where the pairing file goes inside the app's Documents folder
+    pub pairing_path: String,
+    /// Built-in sources can't be removed and aren't saved
+    #[serde(default, skip_deserializing)]
+    pub builtin: bool,
+}
+
+impl SideStoreSource {
+    pub fn url(&self, nightly: bool) -> Result<&str, String> {
+        let url = if nightly {
+            self.nightly_url
+                .as_deref()
+                .ok_or_else(|| format!("{} has no nightly build", self.name))?
+        } else {
+            &self.stable_url
+        };
+        // Sources saved before plain HTTP was rejected
+        check_https(url)?;
+        Ok(url)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.id.trim().is_empty() || self.name.trim().is_empty() {
+            return Err("A source needs an id and a name".to_string());
+        }
+        if self.display_name.trim().is_empty() || self.pairing_path.trim().is_empty() {
+            return Err("A source needs the app's display name and pairing file path".to_string());
+        }
+        for url in std::iter::once(&self.stable_url).chain(&self.nightly_url) {
+            check_https(url)?;
+        }
+        Ok(())
+    }
+}
+
+/// The IPA is signed and installed as downloaded, so it must not come over plain HTTP.
+fn check_https(url: &str) -> Result<(), String> {
+    if !url.starts_with("https://") {
+        return Err(format!("Only HTTPS URLs are supported: {}", url));
+    }
+    Ok(())
+}
+
+fn builtin_sources() -> Vec<SideStoreSource> {
+    vec![
+        SideStoreSource {
+            id: "sidestore".to_string(),
+            name: "SideStore".to_string(),
+            stable_url: "https://github.com/SideStore/SideStore/releases/latest/download/SideStore.ipa".to_string(),
+            nightly_url: Some("https://github.com/SideStore/SideStore/releases/download/nightly/SideStore.ipa".to_string()),
+            display_name: "SideStore".to_string(),
+            pairing_path: "ALTPairingFile.mobiledevicepairing".to_string(),
+            builtin: true,
+        },
+        SideStoreSource {
+            id: "livecontainer".to_string(),
+            name: "LiveContainer+SideStore".to_string(),
+            stable_url: "https://github.com/LiveContainer/LiveContainer/releases/latest/download/LiveContainer+SideStore.ipa".to_string(),
+            nightly_url: Some("https://github.com/LiveContainer/LiveContainer/releases/download/nightly/LiveContainer+SideStore.ipa".to_string()),
+            display_name: "LiveContainer".to_string(),
+            pairing_path: "SideStore/Documents/ALTPairingFile.mobiledevicepairing".to_string(),
+            builtin: true,
+        },
+    ]
+}
+
+impl IloaderCore {
+    /// The built-in sources followed by the ones added by the user.
+    pub fn sidestore_sources(&self) -> Result<Vec<SideStoreSource>, String> {
+        let mut sources = builtin_sources();
+        sources.extend(self.saved_sidestore_sources()?);
+        Ok(sources)
+    }
+
+    pub fn sidestore_source(&self, id: &str) -> Result<SideStoreSource, String> {
+        self.sidestore_sources()?
+            .into_iter()
+            .find(|s| s.id == id)
+            .ok_or_else(|| format!("Unknown SideStore source {}", id))
+    }
+
+    /// Saves `source`, replacing a user-added source with the same id.
+    pub fn add_sidestore_source(&self, mut source: SideStoreSource) -> Result<(), String> {
+        source.validate()?;
+        if builtin_sources().iter().any(|s| s.id == source.id) {
+            return Err(format!("{} is a built-in source", source.id));
+        }
+        source.builtin = false;
+
+        let mut sources = self.saved_sidestore_sources()?;
+        match sources.iter_mut().find(|s| s.id == source.id) {
+            Some(existing) => *existing = source,
+            None => sources.push(source),
+        }
+        self.save_sidestore_sources(&sources)
+    }
+
+    pub fn remove_sidestore_source(&self, id: &str) -> Result<(), String> {
+        if builtin_sources().iter().any(|s| s.id == id) {
+            return Err(format!("{} is a built-in source", id));
+        }
+        let mut sources = self.saved_sidestore_sources()?;
+        sources.retain(|s| s.id != id);
+        self.save_sidestore_sources(&sources)
+    }
+
+    fn saved_sidestore_sources(&self) -> Result<Vec<SideStoreSource>, String> {
+        let path = self.data_dir.join(SOURCES_FILE);
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+        let contents =
+            std::fs::read(&path).map_err(|e| format!("Failed to read sources: {}", e))?;
+        serde_json::from_slice(&contents).map_err(|e| format!("Failed to parse sources: {}", e))
+    }
+
+    fn save_sidestore_sources(&self, sources: &[SideStoreSource]) -> Result<(), String> {
+        std::fs::create_dir_all(&self.data_dir)
+            .map_err(|e| format!("Failed to create data directory: {}", e))?;
+        let contents = serde_json::to_vec_pretty(sources)
+            .map_err(|e| format!("Failed to serialize sources: {}", e))?;
+        std::fs::write(self.data_dir.join(SOURCES_FILE), contents)
+            .map_err(|e| format!("Failed to save sources: {}", e))
+    }
+}
+
+#[tauri::command]
+pub fn list_sidestore_sources(
+    core: State<'_, IloaderCore>,
+) -> Result<Vec<SideStoreSource>, String> {
+    core.sidestore_sources()
+}
+
+#[tauri::command]
+pub fn add_sidestore_source(
+    core: State<'_, IloaderCore>,
+    source: SideStoreSource,
+) -> Result<(), String> {
+    core.add_sidestore_source(source)
+}
+
+#[tauri::command]
+pub fn remove_sidestore_source(core: State<'_, IloaderCore>, id: String) -> Result<(), String> {
+    core.remove_sidestore_source(&id)
+}
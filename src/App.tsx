@@ -7,8 +7,11 @@ import { open as openFileDialog } from "@tauri-apps/plugin-dialog";
 import { openUrl } from "@tauri-apps/plugin-opener";
 import {
   sideloadOperation,
+  sideloadUrlOperation,
+  sideloadBatchOperation,
   installSideStoreOperation,
   installLiveContainerOperation,
+  installFeedAppOperation,
   Operation,
   OperationState,
   OperationUpdate,
@@ -21,11 +24,19 @@ import { Certificates } from "./pages/Certificates";
 import { AppIds } from "./pages/AppIds";
 import { Settings } from "./pages/Settings";
 import { Pairing } from "./pages/Pairing";
+import { SideStoreSource, Sources } from "./pages/Sources";
+import { AltStoreFeedPage } from "./pages/AltStoreFeed";
+import { InstallUrl } from "./pages/InstallUrl";
+import { AppOverrides, IpaDetails, IpaInfo } from "./pages/IpaDetails";
+import { Job, Jobs } from "./pages/Jobs";
+import { History } from "./pages/History";
+import { InstalledApps } from "./pages/InstalledApps";
 import { getVersion } from "@tauri-apps/api/app";
 import { checkForUpdates } from "./update";
 import logo from "./iloader.svg";
 import { GlassCard } from "./components/GlassCard";
 import { useTranslation } from "react-i18next";
+import { useStore } from "./StoreContext";
 
 function App() {
   const { t } = useTranslation();
@@ -36,13 +47,57 @@ function App() {
   const [loggedInAs, setLoggedInAs] = useState<string | null>(null);
   const [selectedDevice, setSelectedDevice] = useState<DeviceInfo | null>(null);
   const [openModal, setOpenModal] = useState<
-    null | "certificates" | "appids" | "pairing"
+    | null
+    | "certificates"
+    | "appids"
+    | "pairing"
+    | "sources"
+    | "altstore"
+    | "install_url"
+    | "jobs"
+    | "history"
+    | "installed_apps"
   >(null);
+  const [customSources, setCustomSources] = useState<SideStoreSource[]>([]);
+  const [inspectedIpa, setInspectedIpa] = useState<{
+    path: string;
+    info: IpaInfo;
+  } | null>(null);
   const [version, setVersion] = useState<string>("");
   const [platform, setPlatform] = useState<"mac" | "windows" | "linux">(
     "windows",
   );
   const refreshDevicesRef = useRef<(() => void) | null>(null);
+  const [connectTimeout] = useStore<number>("connectTimeout", 15);
+  const [readTimeout] = useStore<number>("readTimeout", 30);
+  const [proxy] = useStore<string>("proxy", "");
+  const [extraCaCerts] = useStore<string[]>("extraCaCerts", []);
+  const [autoRefresh] = useStore<boolean>("autoRefresh", false);
+  const [refreshDays] = useStore<number>("refreshDays", 2);
+
+  useEffect(() => {
+    invoke("set_http_config", {
+      config: {
+        connectTimeoutSecs: connectTimeout,
+        readTimeoutSecs: readTimeout,
+        proxy: proxy.trim() || null,
+        extraCaCerts,
+      },
+    });
+  }, [connectTimeout, readTimeout, proxy, extraCaCerts]);
+
+  useEffect(() => {
+    invoke("set_refresh_config", {
+      config: { enabled: autoRefresh, daysBeforeExpiry: refreshDays },
+    });
+  }, [autoRefresh, refreshDays]);
+
+  useEffect(() => {
+    if (openModal !== null) return;
+    invoke<SideStoreSource[]>("list_sidestore_sources")
+      .then((sources) => setCustomSources(sources.filter((s) => !s.builtin)))
+      .catch(() => setCustomSources([]));
+  }, [openModal]);
 
   useEffect(() => {
     const fetchVersion = async () => {
@@ -82,15 +137,19 @@ function App() {
       operation: Operation,
       params: { [key: string]: any },
     ): Promise<void> => {
+      const operationId = crypto.randomUUID();
       setOperationState({
+        id: operationId,
         current: operation,
         started: [],
+        cancelled: [],
+        progress: {},
         failed: [],
         completed: [],
       });
       return new Promise<void>(async (resolve, reject) => {
         const unlistenFn = await listen<OperationUpdate>(
-          "operation_" + operation.id,
+          "operation_" + operationId,
           (event) => {
             setOperationState((old) => {
               if (old == null) return null;
@@ -104,6 +163,19 @@ function App() {
                   ...old,
                   completed: [...old.completed, event.payload.stepId],
                 };
+              } else if (event.payload.updateType === "progress") {
+                return {
+                  ...old,
+                  progress: {
+                    ...old.progress,
+                    [event.payload.stepId]: event.payload.progress,
+                  },
+                };
+              } else if (event.payload.updateType === "cancelled") {
+                return {
+                  ...old,
+                  cancelled: [...old.cancelled, event.payload.stepId],
+                };
               } else if (event.payload.updateType === "failed") {
                 return {
                   ...old,
@@ -121,7 +193,7 @@ function App() {
           },
         );
         try {
-          await invoke(operation.id + "_operation", params);
+          await invoke(operation.id + "_operation", { ...params, operationId });
           unlistenFn();
           resolve();
         } catch (e) {
@@ -133,6 +205,24 @@ function App() {
     [setOperationState],
   );
 
+  const startBatch = useCallback(
+    async (paths: string[]) => {
+      let appPaths: string[];
+      try {
+        appPaths = await invoke<string[]>("list_batch_apps", { paths });
+      } catch (e) {
+        toast.error(t("app.inspect_failed", { error: e }));
+        return;
+      }
+      if (appPaths.length === 0) {
+        toast.error(t("app.no_apps_found"));
+        return;
+      }
+      startOperation(sideloadBatchOperation(appPaths), { appPaths });
+    },
+    [startOperation, t],
+  );
+
   const ensuredLoggedIn = useCallback((): boolean => {
     if (loggedInAs) return true;
     toast.error(t("app.must_be_logged_in"));
@@ -265,6 +355,36 @@ function App() {
                   {shortcutLabel("⌘⇧A", "Ctrl+Shift+A")}
                 </span>
               </button>
+              <button
+                className="workspace-list-item"
+                onClick={() => setOpenModal("sources")}
+              >
+                {t("app.sidestore_sources")}
+              </button>
+              <button
+                className="workspace-list-item"
+                onClick={() => setOpenModal("altstore")}
+              >
+                {t("app.altstore_feed")}
+              </button>
+              <button
+                className="workspace-list-item"
+                onClick={() => setOpenModal("jobs")}
+              >
+                {t("app.jobs")}
+              </button>
+              <button
+                className="workspace-list-item"
+                onClick={() => setOpenModal("history")}
+              >
+                {t("app.history")}
+              </button>
+              <button
+                className="workspace-list-item"
+                onClick={() => setOpenModal("installed_apps")}
+              >
+                {t("app.installed_apps")}
+              </button>
             </div>
           </section>
         </aside>
@@ -299,8 +419,8 @@ function App() {
                   onClick={() => {
                     if (!ensuredLoggedIn() || !ensureSelectedDevice()) return;
                     startOperation(installSideStoreOperation, {
+                      source: "sidestore",
                       nightly: false,
-                      liveContainer: false,
                     });
                   }}
                 >
@@ -310,8 +430,8 @@ function App() {
                   onClick={() => {
                     if (!ensuredLoggedIn() || !ensureSelectedDevice()) return;
                     startOperation(installSideStoreOperation, {
+                      source: "sidestore",
                       nightly: true,
-                      liveContainer: false,
                     });
                   }}
                 >
@@ -321,8 +441,8 @@ function App() {
                   onClick={() => {
                     if (!ensuredLoggedIn() || !ensureSelectedDevice()) return;
                     startOperation(installLiveContainerOperation, {
+                      source: "livecontainer",
                       nightly: false,
-                      liveContainer: true,
                     });
                   }}
                 >
@@ -332,13 +452,35 @@ function App() {
                   onClick={() => {
                     if (!ensuredLoggedIn() || !ensureSelectedDevice()) return;
                     startOperation(installLiveContainerOperation, {
+                      source: "livecontainer",
                       nightly: true,
-                      liveContainer: true,
                     });
                   }}
                 >
                   {t("app.livecontainer_sidestore_nightly")}
                 </button>
+                {customSources.flatMap((source) =>
+                  [false, ...(source.nightlyUrl ? [true] : [])].map(
+                    (nightly) => (
+                      <button
+                        key={`${source.id}-${nightly}`}
+                        onClick={() => {
+                          if (!ensuredLoggedIn() || !ensureSelectedDevice())
+                            return;
+                          startOperation(installSideStoreOperation, {
+                            source: source.id,
+                            nightly,
+                          });
+                        }}
+                      >
+                        {t(
+                          nightly ? "app.source_nightly" : "app.source_stable",
+                          { name: source.name },
+                        )}
+                      </button>
+                    ),
+                  ),
+                )}
                 <button
                   onClick={async () => {
                     if (!ensuredLoggedIn() || !ensureSelectedDevice()) return;
@@ -349,13 +491,51 @@ function App() {
                       ],
                     });
                     if (!path) return;
-                    startOperation(sideloadOperation, {
-                      appPath: path as string,
-                    });
+                    try {
+                      const info = await invoke<IpaInfo>("inspect_ipa", {
+                        path,
+                      });
+                      setInspectedIpa({ path: path as string, info });
+                    } catch (e) {
+                      toast.error(t("app.inspect_failed", { error: e }));
+                    }
                   }}
                 >
                   {t("app.import_ipa")}
                 </button>
+                <button
+                  onClick={() => {
+                    if (!ensuredLoggedIn() || !ensureSelectedDevice()) return;
+                    setOpenModal("install_url");
+                  }}
+                >
+                  {t("app.install_from_url")}
+                </button>
+                <button
+                  onClick={async () => {
+                    if (!ensuredLoggedIn() || !ensureSelectedDevice()) return;
+                    let paths = await openFileDialog({
+                      multiple: true,
+                      filters: [
+                        { name: t("app.ipa_files"), extensions: ["ipa"] },
+                      ],
+                    });
+                    if (!paths || paths.length === 0) return;
+                    startBatch(paths);
+                  }}
+                >
+                  {t("app.import_multiple_ipas")}
+                </button>
+                <button
+                  onClick={async () => {
+                    if (!ensuredLoggedIn() || !ensureSelectedDevice()) return;
+                    let path = await openFileDialog({ directory: true });
+                    if (!path) return;
+                    startBatch([path as string]);
+                  }}
+                >
+                  {t("app.import_ipa_folder")}
+                </button>
               </div>
             </GlassCard>
           </section>
@@ -385,6 +565,80 @@ function App() {
       <Modal isOpen={openModal === "pairing"} close={() => setOpenModal(null)}>
         <Pairing />
       </Modal>
+      <Modal isOpen={openModal === "sources"} close={() => setOpenModal(null)}>
+        <Sources />
+      </Modal>
+      <Modal
+        isOpen={openModal === "altstore"}
+        close={() => setOpenModal(null)}
+      >
+        <AltStoreFeedPage
+          onInstall={(params) => {
+            if (!ensuredLoggedIn() || !ensureSelectedDevice()) return;
+            setOpenModal(null);
+            startOperation(installFeedAppOperation, params);
+          }}
+        />
+      </Modal>
+      <Modal
+        isOpen={inspectedIpa !== null}
+        close={() => setInspectedIpa(null)}
+      >
+        {inspectedIpa && (
+          <IpaDetails
+            info={inspectedIpa.info}
+            onInstall={(overrides: AppOverrides) => {
+              setInspectedIpa(null);
+              startOperation(sideloadOperation, {
+                appPath: inspectedIpa.path,
+                overrides,
+              });
+            }}
+            onQueue={(overrides: AppOverrides) => {
+              if (!ensureSelectedDevice()) return;
+              setInspectedIpa(null);
+              invoke<Job>("enqueue_job", {
+                kind: {
+                  type: "sideload",
+                  appPath: inspectedIpa.path,
+                  overrides,
+                },
+              })
+                .then(() =>
+                  toast.success(
+                    t("app.job_queued", { device: selectedDevice?.name }),
+                  ),
+                )
+                .catch((e) =>
+                  toast.error(t("app.failed_queue", { error: e })),
+                );
+            }}
+          />
+        )}
+      </Modal>
+      <Modal isOpen={openModal === "jobs"} close={() => setOpenModal(null)}>
+        <Jobs />
+      </Modal>
+      <Modal isOpen={openModal === "history"} close={() => setOpenModal(null)}>
+        <History />
+      </Modal>
+      <Modal
+        isOpen={openModal === "installed_apps"}
+        close={() => setOpenModal(null)}
+      >
+        <InstalledApps />
+      </Modal>
+      <Modal
+        isOpen={openModal === "install_url"}
+        close={() => setOpenModal(null)}
+      >
+        <InstallUrl
+          onInstall={(params) => {
+            setOpenModal(null);
+            startOperation(sideloadUrlOperation, params);
+          }}
+        />
+      </Modal>
     </main>
   );
 }
@@ -8,6 +8,13 @@ import { toast } from "sonner";
 import { useStore } from "./StoreContext";
 import { useError } from "./ErrorContext";
 import { Certificate } from "./pages/Certificates";
+
+type AppIdShortfall = {
+  appName: string;
+  required: number;
+  available: number;
+  appIds: { appIdId: string; identifier: string; name: string }[];
+};
 import { useTranslation } from "react-i18next";
 
 const store = await load("data.json");
@@ -35,6 +42,8 @@ export const AppleID = ({
   const [certs, setCerts] = useState<Certificate[] | null>(null);
   const [selectedSerials, setSelectedSerials] = useState<string[]>([]);
   const [chooseCertsOpen, setChooseCertsOpen] = useState<boolean>(false);
+  const [shortfall, setShortfall] = useState<AppIdShortfall | null>(null);
+  const [selectedAppIds, setSelectedAppIds] = useState<string[]>([]);
   const { err } = useError();
 
   useEffect(() => {
@@ -73,6 +82,17 @@ export const AppleID = ({
     };
   }, []);
 
+  useEffect(() => {
+    const unlistenFn = listen("prompt-cancelled", () => {
+      setTfaOpen(false);
+      setCerts(null);
+      setShortfall(null);
+    });
+    return () => {
+      unlistenFn.then((unlisten) => unlisten());
+    };
+  }, []);
+
   const certListenerAdded = useRef<boolean>(false);
   const certUnlisten = useRef<() => void>(() => { });
 
@@ -94,6 +114,16 @@ export const AppleID = ({
     };
   }, []);
 
+  useEffect(() => {
+    const unlistenFn = listen<AppIdShortfall>("app-ids-required", (event) => {
+      setSelectedAppIds([]);
+      setShortfall(event.payload);
+    });
+    return () => {
+      unlistenFn.then((unlisten) => unlisten());
+    };
+  }, []);
+
   return (
     <>
       <h2 style={{ marginTop: 0 }}>{t("apple_id.title")}</h2>
@@ -350,6 +380,65 @@ export const AppleID = ({
           </button>
         </div>
       </Modal>
+      <Modal sizeFit isOpen={shortfall !== null} zIndex={2000}>
+        <h2 className="cert-header">{t("apple_id.app_ids_title")}</h2>
+        <p className="certs-desc">
+          {shortfall &&
+            t("apple_id.app_ids_desc", {
+              name: shortfall.appName,
+              required: shortfall.required,
+              available: shortfall.available,
+            })}
+        </p>
+        {shortfall && (
+          <div className="certs-list">
+            {shortfall.appIds.map((appId) => (
+              <div
+                key={appId.appIdId}
+                className="cert-item"
+                onClick={() => {
+                  setSelectedAppIds((prev) =>
+                    prev.includes(appId.appIdId)
+                      ? prev.filter((id) => id !== appId.appIdId)
+                      : [...prev, appId.appIdId],
+                  );
+                }}
+              >
+                <input
+                  type="checkbox"
+                  id={appId.appIdId}
+                  checked={selectedAppIds.includes(appId.appIdId)}
+                  readOnly
+                />
+                <label htmlFor={appId.appIdId}>
+                  {appId.name} - {appId.identifier}
+                </label>
+              </div>
+            ))}
+          </div>
+        )}
+        <div className="certs-buttons">
+          <button
+            className="action-button danger"
+            disabled={selectedAppIds.length === 0}
+            onClick={async () => {
+              await emit("app-ids-response", selectedAppIds);
+              setShortfall(null);
+            }}
+          >
+            {t("common.delete")}
+          </button>
+          <button
+            className="action-button"
+            onClick={async () => {
+              await emit("app-ids-response", null);
+              setShortfall(null);
+            }}
+          >
+            {t("common.cancel")}
+          </button>
+        </div>
+      </Modal>
     </>
   );
 };
@@ -1,6 +1,8 @@
-import { useCallback, useEffect, useRef, useState } from "react";
+import { Fragment, useCallback, useEffect, useRef, useState } from "react";
 import "./Device.css";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
+import { open as openFileDialog } from "@tauri-apps/plugin-dialog";
 import { toast } from "sonner";
 import { useTranslation } from "react-i18next";
 
@@ -9,8 +11,16 @@ export type DeviceInfo = {
   id: number;
   uuid: string;
   connectionType: "USB" | "Network" | "Unknown";
+  status: DeviceStatus;
+  network?: { address: string; pairingFile: string } | null;
 };
 
+export type DeviceStatus =
+  | { type: "Ready" }
+  | { type: "Locked" }
+  | { type: "NotTrusted" }
+  | { type: "LockdownError"; message: string };
+
 export const Device = ({
   selectedDevice,
   setSelectedDevice,
@@ -68,6 +78,92 @@ export const Device = ({
     loadDevices();
   }, [loadDevices]);
 
+  const trustDevice = useCallback(
+    (device: DeviceInfo) => {
+      const promise = invoke("trust_device_cmd", { device });
+      toast.promise(promise, {
+        loading: t("device.waiting_for_trust"),
+        success: () => {
+          loadDevices();
+          return t("device.trusted");
+        },
+        error: (e) => t("device.trust_failed_prefix") + e,
+      });
+    },
+    [loadDevices, t],
+  );
+
+  const [networkAddress, setNetworkAddress] = useState("");
+
+  const addNetworkDevice = useCallback(async () => {
+    const pairingFile = await openFileDialog({
+      multiple: false,
+      filters: [
+        {
+          name: t("device.pairing_files"),
+          extensions: ["plist", "mobiledevicepairing"],
+        },
+      ],
+    });
+    if (!pairingFile) return;
+    const promise = invoke<DeviceInfo>("add_network_device", {
+      address: networkAddress.trim(),
+      pairingFile,
+    });
+    toast.promise(promise, {
+      loading: t("device.adding_network_device"),
+      success: (device) => {
+        setNetworkAddress("");
+        loadDevices();
+        return t("device.added_network_device", { name: device.name });
+      },
+      error: (e) => t("device.add_network_device_failed_prefix") + e,
+    });
+  }, [networkAddress, loadDevices, t]);
+
+  const removeNetworkDevice = useCallback(
+    (device: DeviceInfo) => {
+      invoke("remove_network_device", { udid: device.uuid })
+        .then(() => loadDevices())
+        .catch((e) => {
+          toast.error(t("device.remove_network_device_failed_prefix") + e);
+        });
+    },
+    [loadDevices, t],
+  );
+
+  const statusLabel = (status: DeviceStatus) => {
+    switch (status.type) {
+      case "Locked":
+        return t("device.status_locked");
+      case "NotTrusted":
+        return t("device.status_not_trusted");
+      case "LockdownError":
+        return t("device.status_error_prefix") + status.message;
+      default:
+        return null;
+    }
+  };
+
+  useEffect(() => {
+    const attached = listen<DeviceInfo>("device-attached", (event) => {
+      setDevices((old) => [
+        ...old.filter((d) => d.id !== event.payload.id),
+        event.payload,
+      ]);
+    });
+    const detached = listen<DeviceInfo>("device-detached", (event) => {
+      setDevices((old) => old.filter((d) => d.id !== event.payload.id));
+      if (selectedDevice?.id === event.payload.id) {
+        setSelectedDevice(null);
+      }
+    });
+    return () => {
+      attached.then((unlisten) => unlisten());
+      detached.then((unlisten) => unlisten());
+    };
+  }, [selectedDevice, setSelectedDevice]);
+
   useEffect(() => {
     registerRefresh?.(loadDevices);
     return () => registerRefresh?.(undefined);
@@ -80,25 +176,56 @@ export const Device = ({
         {devices.length === 0 && <div>{t("device.no_devices_found_period")}</div>}
         {devices.map((device) => {
           const isActive = selectedDevice?.id === device.id;
+          const status = device.status ?? { type: "Ready" };
           return (
-            <button
-              key={device.id}
-              className={"device-card card" + (isActive ? " active" : "")}
-              onClick={() => selectDevice(device)}
-            >
-              <div className="device-meta">
-                <span className="device-name">{device.name}</span>
-                <span className="device-connection">
-                  {device.connectionType}
-                </span>
-              </div>
-              {isActive && (
-                <span className="device-selected-pill">{t("device.selected")}</span>
+            <Fragment key={device.id}>
+              <button
+                className={"device-card card" + (isActive ? " active" : "")}
+                onClick={() => selectDevice(device)}
+              >
+                <div className="device-meta">
+                  <span className="device-name">{device.name}</span>
+                  <span className="device-connection">
+                    {device.connectionType}
+                  </span>
+                  {status.type !== "Ready" && (
+                    <span className="device-status">{statusLabel(status)}</span>
+                  )}
+                </div>
+                {isActive && (
+                  <span className="device-selected-pill">
+                    {t("device.selected")}
+                  </span>
+                )}
+              </button>
+              {status.type === "NotTrusted" && !device.network && (
+                <button onClick={() => trustDevice(device)}>
+                  {t("device.trust")}
+                </button>
+              )}
+              {device.network && (
+                <button onClick={() => removeNetworkDevice(device)}>
+                  {t("device.remove_network_device")}
+                </button>
               )}
-            </button>
+            </Fragment>
           );
         })}
         <button onClick={loadDevices}>{t("common.refresh")}</button>
+        <div className="network-device-form">
+          <input
+            type="text"
+            placeholder={t("device.network_address_placeholder")}
+            value={networkAddress}
+            onChange={(e) => setNetworkAddress(e.target.value)}
+          />
+          <button
+            onClick={addNetworkDevice}
+            disabled={networkAddress.trim() === ""}
+          >
+            {t("device.add_network_device")}
+          </button>
+        </div>
       </div>
     </>
   );
@@ -1,4 +1,4 @@
-import { OperationState } from "./operations";
+import { OperationState, formatBytes } from "./operations";
 import "./OperationView.css";
 import { Modal } from "./Modal";
 import {
@@ -7,6 +7,7 @@ import {
   FaCircleMinus,
 } from "react-icons/fa6";
 import { useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
 import { toast } from "sonner";
 import { openUrl } from "@tauri-apps/plugin-opener";
 import { Trans, useTranslation } from "react-i18next";
@@ -21,10 +22,20 @@ export default ({
   const { t } = useTranslation();
   const operation = operationState.current;
   const opFailed = operationState.failed.length > 0;
+  const opCancelled = operationState.cancelled.length > 0;
+  const settled =
+    operationState.completed.length + operationState.failed.length;
+  // Whether a failed step means the remaining steps won't run
+  const skipRemaining =
+    opFailed &&
+    (!operation.continueOnError ||
+      operationState.failed.some(
+        (f) => operation.steps.find((s) => s.id == f.stepId)?.required,
+      ));
   const done =
-    (opFailed &&
-      operationState.started.length ==
-        operationState.completed.length + operationState.failed.length) ||
+    opCancelled ||
+    (skipRemaining && operationState.started.length == settled) ||
+    (operation.continueOnError && settled == operation.steps.length) ||
     operationState.completed.length == operation.steps.length;
 
   const [moreDetailsOpen, setMoreDetailsOpen] = useState(false);
@@ -46,9 +57,11 @@ export default ({
         </h2>
         <p>
           {done
-            ? opFailed
-              ? t("operation.failed")
-              : t("operation.completed")
+            ? opCancelled
+              ? t("operation.cancelled")
+              : opFailed
+                ? t("operation.failed")
+                : t("operation.completed")
             : t("operation.please_wait")}
         </p>
       </div>
@@ -57,8 +70,13 @@ export default ({
           {operation.steps.map((step) => {
             let failed = operationState.failed.find((f) => f.stepId == step.id);
             let completed = operationState.completed.includes(step.id);
+            let cancelled = operationState.cancelled.includes(step.id);
             let started = operationState.started.includes(step.id);
             let notStarted = !failed && !completed && !started;
+            let progress =
+              started && !completed && !failed
+                ? operationState.progress[step.id]
+                : undefined;
 
             // a little bit gross but it gets the job done.
             let lines =
@@ -77,17 +95,45 @@ export default ({
                   {!failed && completed && (
                     <FaCircleCheck className="operation-check" />
                   )}
-                  {!failed && !completed && started && (
+                  {!failed && !completed && started && !opCancelled && (
                     <div className="loading-icon" />
                   )}
-                  {notStarted && !opFailed && <div className="waiting-icon" />}
-                  {notStarted && opFailed && (
+                  {!failed && !completed && (cancelled || opCancelled) && (
+                    <FaCircleMinus className="operation-skipped" />
+                  )}
+                  {notStarted && !skipRemaining && !opCancelled && (
+                    <div className="waiting-icon" />
+                  )}
+                  {notStarted && skipRemaining && !opCancelled && (
                     <FaCircleMinus className="operation-skipped" />
                   )}
                 </div>
 
                 <div className="operation-step-internal">
-                  <p>{t(step.titleKey)}</p>
+                  <p>{step.title ?? t(step.titleKey)}</p>
+                  {progress && (
+                    <>
+                      {progress.total !== null && progress.total > 0 && (
+                        <progress
+                          className="operation-progress"
+                          value={progress.done}
+                          max={progress.total}
+                        />
+                      )}
+                      <p className="operation-progress-details">
+                        {progress.total !== null
+                          ? t("operation.progress", {
+                              done: formatBytes(progress.done),
+                              total: formatBytes(progress.total),
+                              rate: formatBytes(progress.bytesPerSecond),
+                            })
+                          : t("operation.progress_unknown_total", {
+                              done: formatBytes(progress.done),
+                              rate: formatBytes(progress.bytesPerSecond),
+                            })}
+                      </p>
+                    </>
+                  )}
                   {failed && (
                     <>
                       <pre className="operation-extra-details">
@@ -119,6 +165,14 @@ export default ({
           {t(operation.successMessageKey!)}
         </p>
       )}
+      {done && operation.continueOnError && (
+        <p className="operation-success-message">
+          {t("operation.summary", {
+            completed: operationState.completed.length,
+            total: operation.steps.length,
+          })}
+        </p>
+      )}
       {done && !(!opFailed && operation.successMessageKey) && <p></p>}
       {opFailed && done && (
         <>
@@ -164,6 +218,18 @@ export default ({
           </button>
         </>
       )}
+      {!done && (
+        <button
+          style={{ width: "100%", marginTop: "1.25rem" }}
+          onClick={() => {
+            invoke("cancel_operation", { id: operationState.id }).catch((e) => {
+              toast.error(t("operation.cancel_failed_prefix") + e);
+            });
+          }}
+        >
+          {t("common.cancel")}
+        </button>
+      )}
       {done && (
         <button style={{ width: "100%" }} onClick={closeMenu}>
           {t("common.dismiss")}
@@ -4,17 +4,27 @@ export type Operation = {
   successMessageKey?: string;
   successTitleKey?: string;
   steps: OperationStep[];
+  /** Steps keep running after one fails, e.g. one step per app in a batch */
+  continueOnError?: boolean;
 };
 
 export type OperationStep = {
   id: string;
   titleKey: string;
+  /** Shown instead of `titleKey`, for steps named after user data like a file name */
+  title?: string;
+  /** The remaining steps don't run if this one fails, even with `continueOnError` */
+  required?: boolean;
 };
 
 export type OperationState = {
+  /** Unique per run, used to cancel it and to receive its progress */
+  id: string;
   current: Operation;
   completed: string[];
   started: string[];
+  cancelled: string[];
+  progress: { [stepId: string]: Progress };
   failed: {
     stepId: string;
     extraDetails: string;
@@ -22,8 +32,20 @@ export type OperationState = {
 };
 
 type OperationInfoUpdate = {
-  updateType: "started" | "finished";
+  updateType: "started" | "finished" | "cancelled";
+  stepId: string;
+};
+
+export type Progress = {
+  done: number;
+  total: number | null;
+  bytesPerSecond: number;
+};
+
+type OperationProgressUpdate = {
+  updateType: "progress";
   stepId: string;
+  progress: Progress;
 };
 
 type OperationFailedUpdate = {
@@ -32,7 +54,21 @@ type OperationFailedUpdate = {
   extraDetails: string;
 };
 
-export type OperationUpdate = OperationInfoUpdate | OperationFailedUpdate;
+export type OperationUpdate =
+  | OperationInfoUpdate
+  | OperationProgressUpdate
+  | OperationFailedUpdate;
+
+export const formatBytes = (bytes: number): string => {
+  const units = ["B", "KB", "MB", "GB"];
+  let value = bytes;
+  let unit = 0;
+  while (value >= 1000 && unit < units.length - 1) {
+    value /= 1000;
+    unit++;
+  }
+  return unit === 0 ? `${bytes} B` : `${value.toFixed(1)} ${units[unit]}`;
+};
 
 export const installSideStoreOperation: Operation = {
   id: "install_sidestore",
@@ -80,9 +116,61 @@ export const sideloadOperation = {
   id: "sideload",
   titleKey: "operations.sideload_title",
   steps: [
+    {
+      id: "app_ids",
+      titleKey: "operations.sideload_step_app_ids",
+    },
+    {
+      id: "install",
+      titleKey: "operations.sideload_step_install",
+    },
+  ],
+};
+
+export const installFeedAppOperation = {
+  id: "install_feed_app",
+  titleKey: "operations.install_feed_app_title",
+  steps: [
+    {
+      id: "download",
+      titleKey: "operations.install_feed_app_step_download",
+    },
+    {
+      id: "install",
+      titleKey: "operations.install_feed_app_step_install",
+    },
+  ],
+};
+
+export const sideloadUrlOperation = {
+  id: "sideload_url",
+  titleKey: "operations.sideload_title",
+  steps: [
+    {
+      id: "download",
+      titleKey: "operations.sideload_url_step_download",
+    },
     {
       id: "install",
       titleKey: "operations.sideload_step_install",
     },
   ],
 };
+
+export const sideloadBatchOperation = (appPaths: string[]): Operation => ({
+  id: "sideload_batch",
+  titleKey: "operations.sideload_batch_title",
+  continueOnError: true,
+  steps: [
+    {
+      id: "device",
+      titleKey: "operations.sideload_batch_step_device",
+      required: true,
+    },
+    ...appPaths.map((path, i) => ({
+      id: `install:${i}`,
+      titleKey: "operations.sideload_step_install",
+      title: path.split(/[\\/]/).pop(),
+    })),
+  ],
+});
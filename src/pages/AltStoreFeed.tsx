@@ -0,0 +1,180 @@
+import "./Certificates.css";
+import "./AltStoreFeed.css";
+import { invoke } from "@tauri-apps/api/core";
+import { useCallback, useState } from "react";
+import { toast } from "sonner";
+import { useError } from "../ErrorContext";
+import { useTranslation } from "react-i18next";
+import { formatBytes } from "../components/operations";
+
+export type AltStoreVersion = {
+  version: string;
+  buildVersion: string | null;
+  date: string | null;
+  downloadURL: string;
+  size: number | null;
+  minOSVersion: string | null;
+  maxOSVersion: string | null;
+  sha256: string | null;
+};
+
+export type AltStoreApp = {
+  name: string;
+  bundleIdentifier: string;
+  developerName: string | null;
+  iconURL: string | null;
+  versions: AltStoreVersion[];
+};
+
+export type AltStoreFeed = {
+  name: string;
+  apps: AltStoreApp[];
+};
+
+export type FeedInstallParams = {
+  feedUrl: string;
+  bundleId: string;
+  version: string;
+};
+
+export const AltStoreFeedPage = ({
+  onInstall,
+}: {
+  onInstall: (params: FeedInstallParams) => void;
+}) => {
+  const { t } = useTranslation();
+  const [url, setUrl] = useState("");
+  const [feed, setFeed] = useState<AltStoreFeed | null>(null);
+  const [feedUrl, setFeedUrl] = useState("");
+  const [selected, setSelected] = useState<{ [bundleId: string]: number }>({});
+  const { err } = useError();
+
+  const loadFeed = useCallback(async () => {
+    const source = url.trim();
+    const promise = invoke<AltStoreFeed>("fetch_altstore_feed", {
+      url: source,
+    });
+    promise.then((feed) => {
+      setFeed(feed);
+      setFeedUrl(source);
+      setSelected({});
+    });
+    toast.promise(promise, {
+      loading: t("feed.loading"),
+      success: t("feed.loaded_success"),
+      error: (e) => err(t("feed.failed_load"), e),
+    });
+  }, [url, t]);
+
+  const versionLabel = (version: AltStoreVersion) =>
+    [
+      version.version +
+        (version.buildVersion ? ` (${version.buildVersion})` : ""),
+      version.size !== null
+        ? formatBytes(version.size)
+        : t("feed.unknown_size"),
+      t("feed.min_os", { version: version.minOSVersion ?? "?" }),
+    ].join(" · ");
+
+  return (
+    <>
+      <h2>{t("feed.manage")}</h2>
+      <div className="feed-form">
+        <input
+          className="custom-anisette"
+          type="text"
+          placeholder={t("feed.url")}
+          value={url}
+          onChange={(e) => setUrl(e.target.value)}
+        />
+        <button onClick={loadFeed} disabled={url.trim() === ""}>
+          {t("feed.load")}
+        </button>
+      </div>
+      {feed && (
+        <>
+          <h3>{feed.name}</h3>
+          <div className="card">
+            <div className="certificate-table-container">
+              <table className="certificate-table">
+                <thead>
+                  <tr className="certificate-item">
+                    <th className="cert-item-part">{t("feed.app")}</th>
+                    <th className="cert-item-part">{t("feed.version")}</th>
+                    <th>{t("feed.install")}</th>
+                  </tr>
+                </thead>
+                <tbody>
+                  {feed.apps.length === 0 && (
+                    <tr className="certificate-item cert-item-last">
+                      <td className="cert-item-part">{t("feed.no_apps")}</td>
+                    </tr>
+                  )}
+                  {feed.apps.map((app, i) => {
+                    const index = selected[app.bundleIdentifier] ?? 0;
+                    const version = app.versions[index];
+                    return (
+                      <tr
+                        key={app.bundleIdentifier}
+                        className={
+                          "certificate-item" +
+                          (i === feed.apps.length - 1
+                            ? " cert-item-last"
+                            : "")
+                        }
+                      >
+                        <td className="cert-item-part">
+                          {app.name}
+                          {app.developerName && (
+                            <div className="feed-app-developer">
+                              {app.developerName}
+                            </div>
+                          )}
+                        </td>
+                        <td className="cert-item-part">
+                          <select
+                            value={index}
+                            disabled={app.versions.length === 0}
+                            onChange={(e) =>
+                              setSelected({
+                                ...selected,
+                                [app.bundleIdentifier]: Number(e.target.value),
+                              })
+                            }
+                          >
+                            {app.versions.map((v, j) => (
+                              <option key={j} value={j}>
+                                {versionLabel(v)}
+                              </option>
+                            ))}
+                          </select>
+                        </td>
+                        {version ? (
+                          <td
+                            className="cert-item-revoke"
+                            onClick={() =>
+                              onInstall({
+                                feedUrl,
+                                bundleId: app.bundleIdentifier,
+                                version:
+                                  version.buildVersion ?? version.version,
+                              })
+                            }
+                          >
+                            {t("feed.install")}
+                          </td>
+                        ) : (
+                          <td>-</td>
+                        )}
+                      </tr>
+                    );
+                  })}
+                </tbody>
+              </table>
+            </div>
+          </div>
+        </>
+      )}
+    </>
+  );
+};
@@ -0,0 +1,233 @@
+import "./Certificates.css";
+import "./History.css";
+import { invoke } from "@tauri-apps/api/core";
+import { useCallback, useEffect, useState } from "react";
+import { toast } from "sonner";
+import { useError } from "../ErrorContext";
+import { useTranslation } from "react-i18next";
+
+export type HistoryState = "running" | "succeeded" | "failed" | "cancelled";
+
+export type SigningInfo = {
+  bundleId: string;
+  teamId: string;
+  certificateSerials: string[];
+  expiresAt: string;
+};
+
+export type HistoryStep = {
+  id: string;
+  state: HistoryState;
+  startedAt: string;
+  finishedAt: string | null;
+  error: string | null;
+};
+
+export type HistoryEntry = {
+  id: string;
+  name: string;
+  operationId: string;
+  state: HistoryState;
+  startedAt: string;
+  finishedAt: string | null;
+  email: string | null;
+  devices: string[];
+  apps: string[];
+  signed: SigningInfo[];
+  steps: HistoryStep[];
+};
+
+const formatDate = (date: string) => new Date(date).toLocaleString();
+
+export const History = () => {
+  const { t } = useTranslation();
+  const [entries, setEntries] = useState<HistoryEntry[]>([]);
+  const [selected, setSelected] = useState<HistoryEntry | null>(null);
+  const { err } = useError();
+
+  const loadHistory = useCallback(async () => {
+    try {
+      setEntries(await invoke<HistoryEntry[]>("list_history"));
+    } catch (e) {
+      err(t("history.failed_load"), e);
+    }
+  }, [t]);
+
+  const showEntry = useCallback(
+    async (id: string) => {
+      try {
+        setSelected(await invoke<HistoryEntry>("get_history_entry", { id }));
+      } catch (e) {
+        err(t("history.failed_load"), e);
+      }
+    },
+    [t],
+  );
+
+  const clearHistory = useCallback(async () => {
+    const promise = invoke<void>("clear_history");
+    promise.then(() => {
+      setSelected(null);
+      loadHistory();
+    });
+    toast.promise(promise, {
+      loading: t("history.clearing"),
+      success: t("history.cleared_success"),
+      error: (e) => err(t("history.failed_clear"), e),
+    });
+  }, [loadHistory, t]);
+
+  useEffect(() => {
+    loadHistory();
+  }, []);
+
+  const operationTitle = (name: string) =>
+    t(`operations.${name}_title`, { defaultValue: name });
+
+  if (selected) {
+    return (
+      <>
+        <h2>{operationTitle(selected.name)}</h2>
+        <div className="card">
+          <div className="certificate-table-container">
+            <table className="certificate-table">
+              <tbody>
+                <tr className="certificate-item">
+                  <td className="cert-item-part">{t("history.state")}</td>
+                  <td>{t(`history.states.${selected.state}`)}</td>
+                </tr>
+                <tr className="certificate-item">
+                  <td className="cert-item-part">{t("history.started")}</td>
+                  <td>{formatDate(selected.startedAt)}</td>
+                </tr>
+                <tr className="certificate-item">
+                  <td className="cert-item-part">{t("history.apple_id")}</td>
+                  <td>{selected.email ?? "-"}</td>
+                </tr>
+                <tr className="certificate-item">
+                  <td className="cert-item-part">{t("history.devices")}</td>
+                  <td className="history-detail">
+                    {selected.devices.join(", ") || "-"}
+                  </td>
+                </tr>
+                <tr className="certificate-item">
+                  <td className="cert-item-part">{t("history.apps")}</td>
+                  <td className="history-detail">
+                    {selected.apps.map((app) => (
+                      <div key={app}>{app}</div>
+                    ))}
+                  </td>
+                </tr>
+                <tr className="certificate-item cert-item-last">
+                  <td className="cert-item-part">{t("history.signed")}</td>
+                  <td className="history-detail">
+                    {selected.signed.map((signed) => (
+                      <div key={signed.bundleId}>
+                        {t("history.signed_details", {
+                          bundleId: signed.bundleId,
+                          teamId: signed.teamId,
+                          serials: signed.certificateSerials.join(", "),
+                          expires: formatDate(signed.expiresAt),
+                        })}
+                      </div>
+                    ))}
+                  </td>
+                </tr>
+              </tbody>
+            </table>
+          </div>
+        </div>
+        <h3>{t("history.steps")}</h3>
+        <div className="card">
+          <div className="certificate-table-container">
+            <table className="certificate-table">
+              <tbody>
+                {selected.steps.map((step, i) => (
+                  <tr
+                    key={step.id + i}
+                    className={
+                      "certificate-item" +
+                      (i === selected.steps.length - 1 ? " cert-item-last" : "")
+                    }
+                  >
+                    <td className="cert-item-part">
+                      {formatDate(step.startedAt)}
+                    </td>
+                    <td className="cert-item-part">{step.id}</td>
+                    <td
+                      className={
+                        step.state === "failed" ? "history-failed" : undefined
+                      }
+                    >
+                      {t(`history.states.${step.state}`)}
+                      {step.error && (
+                        <div className="history-detail">{step.error}</div>
+                      )}
+                    </td>
+                  </tr>
+                ))}
+              </tbody>
+            </table>
+          </div>
+        </div>
+        <button onClick={() => setSelected(null)}>{t("history.back")}</button>
+      </>
+    );
+  }
+
+  return (
+    <>
+      <h2>{t("history.title")}</h2>
+      <div className="card">
+        {entries.length === 0 ? (
+          <div>{t("history.empty")}</div>
+        ) : (
+          <div className="certificate-table-container">
+            <table className="certificate-table">
+              <thead>
+                <tr className="certificate-item">
+                  <th className="cert-item-part">{t("history.started")}</th>
+                  <th className="cert-item-part">{t("history.operation")}</th>
+                  <th className="cert-item-part">{t("history.apps")}</th>
+                  <th>{t("history.state")}</th>
+                </tr>
+              </thead>
+              <tbody>
+                {entries.map((entry, i) => (
+                  <tr
+                    key={entry.id}
+                    className={
+                      "certificate-item history-entry" +
+                      (i === entries.length - 1 ? " cert-item-last" : "")
+                    }
+                    onClick={() => showEntry(entry.id)}
+                  >
+                    <td className="cert-item-part">
+                      {formatDate(entry.startedAt)}
+                    </td>
+                    <td className="cert-item-part">
+                      {operationTitle(entry.name)}
+                    </td>
+                    <td className="cert-item-part history-detail">
+                      {entry.apps
+                        .map((app) => app.split(/[\\/]/).pop())
+                        .join(", ") || "-"}
+                    </td>
+                    <td
+                      className={
+                        entry.state === "failed" ? "history-failed" : undefined
+                      }
+                    >
+                      {t(`history.states.${entry.state}`)}
+                    </td>
+                  </tr>
+                ))}
+              </tbody>
+            </table>
+          </div>
+        )}
+      </div>
+      <button onClick={clearHistory}>{t("history.clear")}</button>
+    </>
+  );
+};
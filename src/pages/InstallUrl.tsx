@@ -0,0 +1,53 @@
+import "./Sources.css";
+import { useState } from "react";
+import { useTranslation } from "react-i18next";
+
+export type InstallUrlParams = {
+  url: string;
+  sha256: string | null;
+};
+
+export const InstallUrl = ({
+  onInstall,
+}: {
+  onInstall: (params: InstallUrlParams) => void;
+}) => {
+  const { t } = useTranslation();
+  const [url, setUrl] = useState("");
+  const [sha256, setSha256] = useState("");
+
+  return (
+    <>
+      <h2>{t("install_url.title")}</h2>
+      <div className="source-form">
+        <label className="settings-label has-dropdown">
+          {t("install_url.url")}
+          <input
+            className="custom-anisette"
+            type="text"
+            placeholder="https://"
+            value={url}
+            onChange={(e) => setUrl(e.target.value)}
+          />
+        </label>
+        <label className="settings-label has-dropdown">
+          {t("install_url.sha256_optional")}
+          <input
+            className="custom-anisette"
+            type="text"
+            value={sha256}
+            onChange={(e) => setSha256(e.target.value)}
+          />
+        </label>
+        <button
+          onClick={() =>
+            onInstall({ url: url.trim(), sha256: sha256.trim() || null })
+          }
+          disabled={!url.trim().startsWith("https://")}
+        >
+          {t("install_url.install")}
+        </button>
+      </div>
+    </>
+  );
+};
@@ -0,0 +1,121 @@
+import "./Certificates.css";
+import "./History.css";
+import { invoke } from "@tauri-apps/api/core";
+import { useCallback, useEffect, useState } from "react";
+import { toast } from "sonner";
+import { useError } from "../ErrorContext";
+import { useTranslation } from "react-i18next";
+import { AppOverrides } from "./IpaDetails";
+import { JobKind } from "./Jobs";
+
+export type ExpiringApp = {
+  bundleId: string;
+  udid: string;
+  teamId: string;
+  certificateSerials: string[];
+  expiresAt: string;
+  installedAt: string;
+  appPath: string;
+  overrides: AppOverrides;
+  /** What refreshes run to install the app again */
+  origin: JobKind | null;
+  name: string | null;
+  expired: boolean;
+  /** Rounded up, 0 once expired */
+  daysRemaining: number;
+  /** Rounded up, 0 once expired */
+  hoursRemaining: number;
+};
+
+/** Apps with this many days or fewer left are highlighted */
+const EXPIRING_SOON_DAYS = 2;
+
+export const InstalledApps = () => {
+  const { t } = useTranslation();
+  const [apps, setApps] = useState<ExpiringApp[] | null>(null);
+  const { err } = useError();
+
+  const loadApps = useCallback(async () => {
+    const promise = invoke<ExpiringApp[]>("list_installed_apps");
+    promise.then(setApps);
+    toast.promise(promise, {
+      loading: t("installed_apps.loading"),
+      success: t("installed_apps.loaded_success"),
+      error: (e) => err(t("installed_apps.failed_load"), e),
+    });
+  }, [t]);
+
+  useEffect(() => {
+    loadApps();
+  }, []);
+
+  return (
+    <>
+      <h2>{t("installed_apps.title")}</h2>
+      <div className="card">
+        {apps === null ? (
+          <div>{t("installed_apps.loading")}</div>
+        ) : apps.length === 0 ? (
+          <div>{t("installed_apps.none")}</div>
+        ) : (
+          <div className="certificate-table-container">
+            <table className="certificate-table">
+              <thead>
+                <tr className="certificate-item">
+                  <th className="cert-item-part">{t("installed_apps.app")}</th>
+                  <th className="cert-item-part">
+                    {t("installed_apps.device")}
+                  </th>
+                  <th className="cert-item-part">
+                    {t("installed_apps.expires")}
+                  </th>
+                  <th>{t("installed_apps.days_remaining")}</th>
+                </tr>
+              </thead>
+              <tbody>
+                {apps.map((app, i) => (
+                  <tr
+                    key={app.udid + app.bundleId}
+                    className={
+                      "certificate-item" +
+                      (i === apps.length - 1 ? " cert-item-last" : "")
+                    }
+                  >
+                    <td className="cert-item-part">
+                      {app.name ?? app.bundleId}
+                      <div className="history-detail">{app.bundleId}</div>
+                    </td>
+                    <td className="cert-item-part history-detail">
+                      {app.udid}
+                    </td>
+                    <td className="cert-item-part">
+                      {new Date(app.expiresAt).toLocaleString()}
+                    </td>
+                    <td
+                      className={
+                        app.daysRemaining <= EXPIRING_SOON_DAYS
+                          ? "history-failed"
+                          : undefined
+                      }
+                    >
+                      {app.expired
+                        ? t("installed_apps.expired")
+                        : app.daysRemaining <= 1
+                          ? t("installed_apps.hours", {
+                              count: app.hoursRemaining,
+                            })
+                          : t("installed_apps.days", {
+                              count: app.daysRemaining,
+                            })}
+                    </td>
+                  </tr>
+                ))}
+              </tbody>
+            </table>
+          </div>
+        )}
+      </div>
+      <button onClick={loadApps}>{t("installed_apps.refresh")}</button>
+    </>
+  );
+};
@@ -0,0 +1,173 @@
+import "./Certificates.css";
+import "./IpaDetails.css";
+import "./Sources.css";
+import { invoke } from "@tauri-apps/api/core";
+import { useEffect, useState } from "react";
+import { useTranslation } from "react-i18next";
+
+export type IpaInfo = {
+  bundleId: string;
+  name: string;
+  version: string | null;
+  build: string | null;
+  minimumOsVersion: string | null;
+  extensions: { bundleId: string; name: string }[];
+  entitlements: Record<string, any>;
+  icon: string | null;
+  appIdsRequired: number;
+};
+
+export type AppOverrides = {
+  bundleId: string | null;
+  name: string | null;
+  version: string | null;
+};
+
+type AppIdsResponse = {
+  appIds: { identifier: string }[];
+  maxQuantity: number | null;
+  availableQuantity: number | null;
+};
+
+export const IpaDetails = ({
+  info,
+  onInstall,
+  onQueue,
+}: {
+  info: IpaInfo;
+  onInstall: (overrides: AppOverrides) => void;
+  onQueue: (overrides: AppOverrides) => void;
+}) => {
+  const { t } = useTranslation();
+  const [available, setAvailable] = useState<number | null>(null);
+  const [bundleId, setBundleId] = useState("");
+  const [name, setName] = useState("");
+  const [version, setVersion] = useState("");
+
+  useEffect(() => {
+    invoke<AppIdsResponse>("list_app_ids")
+      .then((list) => setAvailable(list.availableQuantity))
+      .catch(() => setAvailable(null));
+  }, [info]);
+
+  const entitlements = Object.keys(info.entitlements);
+
+  const overrides = (): AppOverrides => ({
+    bundleId: bundleId.trim() || null,
+    name: name.trim() || null,
+    version: version.trim() || null,
+  });
+
+  return (
+    <>
+      <div className="ipa-header">
+        {info.icon && (
+          <img
+            className="ipa-icon"
+            src={`data:image/png;base64,${info.icon}`}
+            alt=""
+          />
+        )}
+        <div>
+          <h2>{info.name}</h2>
+          <div className="ipa-bundle-id">{info.bundleId}</div>
+        </div>
+      </div>
+      <div className="card">
+        <div className="certificate-table-container">
+          <table className="certificate-table">
+            <tbody>
+              <tr className="certificate-item">
+                <td className="cert-item-part">{t("ipa.version")}</td>
+                <td>
+                  {info.version ?? "?"} ({info.build ?? "?"})
+                </td>
+              </tr>
+              <tr className="certificate-item">
+                <td className="cert-item-part">{t("ipa.minimum_ios")}</td>
+                <td>{info.minimumOsVersion ?? "?"}</td>
+              </tr>
+              <tr className="certificate-item">
+                <td className="cert-item-part">{t("ipa.extensions")}</td>
+                <td>
+                  {info.extensions.length === 0
+                    ? t("ipa.none")
+                    : info.extensions.map((extension) => (
+                        <div key={extension.bundleId}>
+                          {extension.name} ({extension.bundleId})
+                        </div>
+                      ))}
+                </td>
+              </tr>
+              <tr className="certificate-item">
+                <td className="cert-item-part">{t("ipa.entitlements")}</td>
+                <td>
+                  {entitlements.length === 0
+                    ? t("ipa.none")
+                    : entitlements.map((key) => <div key={key}>{key}</div>)}
+                </td>
+              </tr>
+              <tr className="certificate-item cert-item-last">
+                <td className="cert-item-part">{t("ipa.app_ids")}</td>
+                <td>
+                  {available === null
+                    ? info.appIdsRequired
+                    : t("ipa.app_ids_available", {
+                        required: info.appIdsRequired,
+                        available,
+                      })}
+                </td>
+              </tr>
+            </tbody>
+          </table>
+        </div>
+      </div>
+      {available !== null && info.appIdsRequired > available && (
+        <p className="ipa-warning">
+          {t("ipa.app_ids_warning", {
+            required: info.appIdsRequired,
+            available,
+          })}
+        </p>
+      )}
+      <h3>{t("ipa.overrides")}</h3>
+      <p>{t("ipa.overrides_desc")}</p>
+      <div className="source-form">
+        <label className="settings-label has-dropdown">
+          {t("ipa.bundle_id")}
+          <input
+            className="custom-anisette"
+            type="text"
+            placeholder={info.bundleId}
+            value={bundleId}
+            onChange={(e) => setBundleId(e.target.value)}
+          />
+        </label>
+        <label className="settings-label has-dropdown">
+          {t("ipa.name")}
+          <input
+            className="custom-anisette"
+            type="text"
+            placeholder={info.name}
+            value={name}
+            onChange={(e) => setName(e.target.value)}
+          />
+        </label>
+        <label className="settings-label has-dropdown">
+          {t("ipa.version")}
+          <input
+            className="custom-anisette"
+            type="text"
+            placeholder={info.version ?? ""}
+            value={version}
+            onChange={(e) => setVersion(e.target.value)}
+          />
+        </label>
+        <button onClick={() => onInstall(overrides())}>
+          {t("ipa.install")}
+        </button>
+        <button onClick={() => onQueue(overrides())}>{t("ipa.queue")}</button>
+      </div>
+    </>
+  );
+};
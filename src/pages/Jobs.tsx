@@ -0,0 +1,172 @@
+import "./Certificates.css";
+import { invoke } from "@tauri-apps/api/core";
+import { useCallback, useEffect, useState } from "react";
+import { toast } from "sonner";
+import { useError } from "../ErrorContext";
+import { useTranslation } from "react-i18next";
+import { AppOverrides } from "./IpaDetails";
+
+export type JobKind =
+  | { type: "sideload"; appPath: string; overrides?: AppOverrides }
+  | { type: "sideloadUrl"; url: string; sha256: string | null }
+  | {
+      type: "installSidestore";
+      source: string;
+      nightly: boolean;
+      sha256: string | null;
+    }
+  | {
+      type: "installFeedApp";
+      feedUrl: string;
+      bundleId: string;
+      version: string | null;
+    };
+
+export type JobState =
+  | "pending"
+  | "running"
+  | "succeeded"
+  | "failed"
+  | "cancelled";
+
+export type Job = {
+  id: string;
+  kind: JobKind;
+  udid: string;
+  state: JobState;
+  attempts: number;
+  error: string | null;
+  createdAt: string;
+  updatedAt: string;
+};
+
+const jobLabel = (kind: JobKind) => {
+  switch (kind.type) {
+    case "sideload":
+      return kind.appPath.split(/[\\/]/).pop() ?? kind.appPath;
+    case "sideloadUrl":
+      return kind.url;
+    case "installSidestore":
+      return "SideStore";
+    case "installFeedApp":
+      return kind.bundleId;
+  }
+};
+
+export const Jobs = () => {
+  const { t } = useTranslation();
+  const [jobs, setJobs] = useState<Job[]>([]);
+  const { err } = useError();
+
+  const loadJobs = useCallback(async () => {
+    try {
+      setJobs(await invoke<Job[]>("list_jobs"));
+    } catch (e) {
+      err(t("jobs.failed_load"), e);
+    }
+  }, [t]);
+
+  const cancelJob = useCallback(
+    async (id: string) => {
+      try {
+        await invoke<void>("cancel_job", { id });
+        loadJobs();
+      } catch (e) {
+        err(t("jobs.failed_cancel"), e);
+      }
+    },
+    [loadJobs, t],
+  );
+
+  const retryJob = useCallback(
+    async (id: string) => {
+      try {
+        await invoke<Job>("retry_job", { id });
+        loadJobs();
+      } catch (e) {
+        err(t("jobs.failed_retry"), e);
+      }
+    },
+    [loadJobs, t],
+  );
+
+  const clearFinished = useCallback(async () => {
+    const promise = invoke<void>("clear_finished_jobs");
+    promise.then(loadJobs);
+    toast.promise(promise, {
+      loading: t("jobs.clearing"),
+      success: t("jobs.cleared_success"),
+      error: (e) => err(t("jobs.failed_clear"), e),
+    });
+  }, [loadJobs, t]);
+
+  // Jobs progress in the background, so keep the list current while it is open
+  useEffect(() => {
+    loadJobs();
+    const interval = setInterval(loadJobs, 2000);
+    return () => clearInterval(interval);
+  }, [loadJobs]);
+
+  return (
+    <>
+      <h2>{t("jobs.manage")}</h2>
+      <p>{t("jobs.description")}</p>
+      <div className="card">
+        {jobs.length === 0 ? (
+          <div>{t("jobs.no_jobs")}</div>
+        ) : (
+          <div className="certificate-table-container">
+            <table className="certificate-table">
+              <thead>
+                <tr className="certificate-item">
+                  <th className="cert-item-part">{t("jobs.app")}</th>
+                  <th className="cert-item-part">{t("jobs.device")}</th>
+                  <th className="cert-item-part">{t("jobs.state")}</th>
+                  <th className="cert-item-part">{t("jobs.attempts")}</th>
+                  <th>{t("jobs.action")}</th>
+                </tr>
+              </thead>
+              <tbody>
+                {jobs.map((job, i) => (
+                  <tr
+                    key={job.id}
+                    className={
+                      "certificate-item" +
+                      (i === jobs.length - 1 ? " cert-item-last" : "")
+                    }
+                  >
+                    <td className="cert-item-part">{jobLabel(job.kind)}</td>
+                    <td className="cert-item-part">{job.udid}</td>
+                    <td className="cert-item-part" title={job.error ?? ""}>
+                      {t(`jobs.states.${job.state}`)}
+                    </td>
+                    <td className="cert-item-part">{job.attempts}</td>
+                    {job.state === "pending" || job.state === "running" ? (
+                      <td
+                        className="cert-item-revoke"
+                        onClick={() => cancelJob(job.id)}
+                      >
+                        {t("jobs.cancel")}
+                      </td>
+                    ) : job.state === "failed" ||
+                      job.state === "cancelled" ? (
+                      <td
+                        className="cert-item-revoke"
+                        onClick={() => retryJob(job.id)}
+                      >
+                        {t("jobs.retry")}
+                      </td>
+                    ) : (
+                      <td></td>
+                    )}
+                  </tr>
+                ))}
+              </tbody>
+            </table>
+          </div>
+        )}
+      </div>
+      <button onClick={clearFinished}>{t("jobs.clear_finished")}</button>
+    </>
+  );
+};
@@ -0,0 +1,157 @@
+import "./Certificates.css";
+import "./Sources.css";
+import { invoke } from "@tauri-apps/api/core";
+import { useCallback, useEffect, useState } from "react";
+import { toast } from "sonner";
+import { useError } from "../ErrorContext";
+import { useTranslation } from "react-i18next";
+
+export type SideStoreSource = {
+  id: string;
+  name: string;
+  stableUrl: string;
+  nightlyUrl: string | null;
+  displayName: string;
+  pairingPath: string;
+  builtin: boolean;
+};
+
+const emptySource: SideStoreSource = {
+  id: "",
+  name: "",
+  stableUrl: "",
+  nightlyUrl: null,
+  displayName: "SideStore",
+  pairingPath: "ALTPairingFile.mobiledevicepairing",
+  builtin: false,
+};
+
+export const Sources = () => {
+  const { t } = useTranslation();
+  const [sources, setSources] = useState<SideStoreSource[]>([]);
+  const [newSource, setNewSource] = useState<SideStoreSource>(emptySource);
+  const { err } = useError();
+
+  const loadSources = useCallback(async () => {
+    try {
+      setSources(await invoke<SideStoreSource[]>("list_sidestore_sources"));
+    } catch (e) {
+      err(t("sources.failed_load"), e);
+    }
+  }, [t]);
+
+  const addSource = useCallback(async () => {
+    const promise = invoke<void>("add_sidestore_source", {
+      source: {
+        ...newSource,
+        nightlyUrl: newSource.nightlyUrl?.trim() || null,
+      },
+    });
+    promise.then(() => {
+      setNewSource(emptySource);
+      loadSources();
+    });
+    toast.promise(promise, {
+      loading: t("sources.adding"),
+      success: t("sources.added_success"),
+      error: (e) => err(t("sources.failed_add"), e),
+    });
+  }, [newSource, loadSources, t]);
+
+  const removeSource = useCallback(
+    async (id: string) => {
+      const promise = invoke<void>("remove_sidestore_source", { id });
+      promise.then(loadSources);
+      toast.promise(promise, {
+        loading: t("sources.removing"),
+        success: t("sources.removed_success"),
+        error: (e) => err(t("sources.failed_remove"), e),
+      });
+    },
+    [loadSources, t],
+  );
+
+  useEffect(() => {
+    loadSources();
+  }, []);
+
+  const field = (key: keyof SideStoreSource, label: string) => (
+    <label className="settings-label has-dropdown">
+      {label}
+      <input
+        className="custom-anisette"
+        type="text"
+        value={(newSource[key] as string | null) ?? ""}
+        onChange={(e) => setNewSource({ ...newSource, [key]: e.target.value })}
+      />
+    </label>
+  );
+
+  return (
+    <>
+      <h2>{t("sources.manage")}</h2>
+      <div className="card">
+        <div className="certificate-table-container">
+          <table className="certificate-table">
+            <thead>
+              <tr className="certificate-item">
+                <th className="cert-item-part">{t("sources.name")}</th>
+                <th className="cert-item-part">{t("sources.stable_url")}</th>
+                <th className="cert-item-part">{t("sources.nightly_url")}</th>
+                <th>{t("common.remove")}</th>
+              </tr>
+            </thead>
+            <tbody>
+              {sources.map((source, i) => (
+                <tr
+                  key={source.id}
+                  className={
+                    "certificate-item" +
+                    (i === sources.length - 1 ? " cert-item-last" : "")
+                  }
+                >
+                  <td className="cert-item-part">{source.name}</td>
+                  <td className="cert-item-part source-url">
+                    {source.stableUrl}
+                  </td>
+                  <td className="cert-item-part source-url">
+                    {source.nightlyUrl ?? "-"}
+                  </td>
+                  {source.builtin ? (
+                    <td>{t("sources.builtin")}</td>
+                  ) : (
+                    <td
+                      className="cert-item-revoke"
+                      onClick={() => removeSource(source.id)}
+                    >
+                      {t("common.remove")}
+                    </td>
+                  )}
+                </tr>
+              ))}
+            </tbody>
+          </table>
+        </div>
+      </div>
+      <h3>{t("sources.add")}</h3>
+      <div className="source-form">
+        {field("id", t("sources.id"))}
+        {field("name", t("sources.name"))}
+        {field("stableUrl", t("sources.stable_url"))}
+        {field("nightlyUrl", t("sources.nightly_url_optional"))}
+        {field("displayName", t("sources.display_name"))}
+        {field("pairingPath", t("sources.pairing_path"))}
+        <button
+          onClick={addSource}
+          disabled={
+            newSource.id.trim() === "" ||
+            newSource.name.trim() === "" ||
+            newSource.stableUrl.trim() === ""
+          }
+        >
+          {t("sources.add")}
+        </button>
+      </div>
+    </>
+  );
+};